@@ -1,51 +1,155 @@
-use std::cell::RefCell;
-use std::rc::Rc;
+use std::cell::{Cell, RefCell};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufReader, Read, Seek, SeekFrom, Write};
+use std::iter::{Peekable, Take};
+use std::path::{Path, PathBuf};
+use std::rc::{Rc, Weak};
 
-type Link = Option<Rc<RefCell<Node>>>;
+mod persistent;
+mod sync;
 
-#[derive(Debug, Clone)]
-pub struct Node {
-    value: String,
-    prev: Link,
-    next: Link,
+pub use persistent::PersistentTransactionLog;
+pub use sync::{SyncIter, SyncTransactionLog};
+
+type Link<T> = Option<Rc<RefCell<Node<T>>>>;
+// Links back towards the head are weak so that adjacent nodes don't keep each
+// other alive, only the `next` chain owns nodes.
+type WeakLink<T> = Option<Weak<RefCell<Node<T>>>>;
+
+#[derive(Debug)]
+pub struct Node<T> {
+    value: T,
+    // Sequence id handed out by the owning `TransactionLog` on append.
+    id: u64,
+    // Number of times the value has been edited in place.
+    generation: u64,
+    prev: WeakLink<T>,
+    next: Link<T>,
 }
 
-impl Node {
-    fn new(value: String) -> Rc<RefCell<Node>> {
+impl<T> Node<T> {
+    fn new(value: T, id: u64) -> Rc<RefCell<Node<T>>> {
         Rc::new(RefCell::new(Node {
             value,
+            id,
+            generation: 0,
             prev: None,
             next: None,
         }))
     }
+
+    /// Take the `prev` link, upgraded to a strong one.
+    fn take_prev(&mut self) -> Link<T> {
+        self.prev.take().and_then(|prev| prev.upgrade())
+    }
 }
 
+/// A doubly linked log of values, `String`s unless stated otherwise.
 #[derive(Debug)]
-pub struct TransactionLog {
-    head: Link,
-    tail: Link,
+pub struct TransactionLog<T = String> {
+    head: Link<T>,
+    tail: Link<T>,
     pub length: usize,
+    // Sequence id the next appended node will get.
+    next_id: u64,
+    tee: Option<Tee<T>>,
+    spill: Option<Spill<T>>,
+    // Most entries `append` keeps before evicting the oldest, if capped.
+    capacity: Option<usize>,
+    // Entries taken back by `undo`, the most recent last.
+    redo: Vec<T>,
+    // Nesting depth of `deferring` calls.
+    deferring: usize,
+    // Running checksum kept up to date by most edits, `None` once an edit
+    // made it stale, see `checksum`.
+    checksum: Cell<Option<RunningChecksum<T>>>,
 }
 
-impl TransactionLog {
-    pub fn new() -> TransactionLog {
+impl<T> TransactionLog<T> {
+    pub fn new() -> TransactionLog<T> {
         TransactionLog {
             head: None,
             tail: None,
             length: 0,
+            next_id: 1,
+            tee: None,
+            spill: None,
+            capacity: None,
+            redo: Vec::new(),
+            deferring: 0,
+            checksum: Cell::new(None),
+        }
+    }
+
+    /// Create a `TransactionLog` that keeps at most `capacity` entries, see
+    /// [`set_capacity`].
+    ///
+    /// [`set_capacity`]: TransactionLog::set_capacity
+    pub fn with_capacity(capacity: usize) -> TransactionLog<T> {
+        let mut log = TransactionLog::new();
+        log.capacity = Some(capacity);
+        log
+    }
+
+    /// Cap the `TransactionLog` at `capacity` entries, or lift the cap with
+    /// `None`, which is the default. Once capped, every edit that takes the
+    /// log past the cap evicts and drops entries from the head, so a cap of 0
+    /// keeps nothing and `append_front` on a full log drops the value it just
+    /// inserted. Entries over a new cap are evicted right away.
+    ///
+    /// Eviction is held off while a savepoint, script or delta is applied. An
+    /// eviction counts as removing the head for [`rollback_to`].
+    ///
+    /// [`rollback_to`]: TransactionLog::rollback_to
+    pub fn set_capacity(&mut self, capacity: Option<usize>) {
+        self.capacity = capacity;
+        self.evict_over_capacity();
+    }
+
+    fn evict_over_capacity(&mut self) {
+        if self.deferring > 0 {
+            return;
         }
+        if let Some(capacity) = self.capacity {
+            while self.length > capacity {
+                self.pop();
+            }
+        }
+    }
+
+    /// Build a `TransactionLog` by calling `f` and appending every value it
+    /// returns, stopping at the first `None`.
+    pub fn from_fn<F: FnMut() -> Option<T>>(f: F) -> TransactionLog<T> {
+        std::iter::from_fn(f).collect()
     }
 
-    /// Append a new value at the end of the `TransactionLog`.
-    pub fn append(&mut self, value: String) {
-        let new_node = Node::new(value);
+    /// Append a new value at the end of the `TransactionLog`. This starts a
+    /// new history, so nothing undone before can be redone.
+    pub fn append(&mut self, value: T) {
+        self.redo.clear();
+        self.push_back(value);
+    }
+
+    /// Append `value` at the tail, leaving the redo history alone.
+    fn push_back(&mut self, value: T) {
+        if let Some(tee) = self.tee.as_mut() {
+            tee.write_line(&value);
+        }
+        self.checksum_in(&value);
+        let new_node = Node::new(value, self.next_id);
+        self.next_id += 1;
         match self.tail.take() {
             // Go directly to the tail and add new_node to the next of the tail
             // node. Also assign the old tail node to the `prev` of the
             // new_node.
             Some(old_node) => {
                 old_node.borrow_mut().next = Some(new_node.clone());
-                new_node.borrow_mut().prev = Some(old_node);
+                new_node.borrow_mut().prev = Some(Rc::downgrade(&old_node));
             }
             // If tail is None, TransactionLog must have been empty so the head
             // must be None too. Assign new_node to head, assignment to tail
@@ -56,163 +160,4496 @@ impl TransactionLog {
         // Always add new_node to the tail of the TransactionLog. That's the
         // whole purpose of append.
         self.tail = Some(new_node);
+        self.evict_over_capacity();
+        self.spill_overflow();
     }
 
-    /// Pop a value from the front of the `TransactionLog`.
-    pub fn pop(&mut self) -> Option<String> {
-        // Note `take()` returns an `Option<T>`, and calling `map()` on that
-        // will map the supplied function over the inner T. The `Option` wrapper
-        // will remain and be returned.
-        self.head.take().map(|head_node| {
-            // There is a head node, we borrow it and take the next node
-            // assigning it to the head field of `TransactionLog`. Note we first
-            // assign the `prev` field of the next node to None, since the node
-            // being pointed to by `prev` is being popped.
-            if let Some(next_node) = head_node.borrow_mut().next.take() {
-                next_node.borrow_mut().prev = None;
-                self.head = Some(next_node);
-            // There is no head node, remove the `TransactionLog` tail as well
-            // to create an empty `TransactionLog`.
-            } else {
-                self.tail.take();
+    /// Insert a new value at the front of the `TransactionLog`.
+    pub fn append_front(&mut self, value: T) {
+        let new_node = Node::new(value, self.next_id);
+        self.next_id += 1;
+        self.push_front_node(new_node);
+    }
+
+    /// Link a detached node in at the head, keeping its id and generation.
+    fn push_front_node(&mut self, new_node: Rc<RefCell<Node<T>>>) {
+        self.checksum_in(&new_node.borrow().value);
+        match self.head.take() {
+            // Link the old head node and new_node to each other, new_node
+            // goes in front.
+            Some(old_node) => {
+                old_node.borrow_mut().prev = Some(Rc::downgrade(&new_node));
+                new_node.borrow_mut().next = Some(old_node);
             }
-            self.length -= 1;
+            // If head is None, TransactionLog must have been empty so new_node
+            // is the tail as well.
+            None => self.tail = Some(new_node.clone()),
+        };
+        self.length += 1;
+        self.head = Some(new_node);
+        self.evict_over_capacity();
+    }
+
+    /// Pop a value from the front of the `TransactionLog`.
+    pub fn pop(&mut self) -> Option<T> {
+        // Note `take_front()` returns an `Option`, and calling `map()` on that
+        // will map the supplied function over the inner node. The `Option`
+        // wrapper will remain and be returned.
+        self.take_front().map(|head_node| {
             // This should remove the `Rc`, resulting in a `RefCell`. Unless for
             // some reason something else was holding a reference to the head
             // node.
             Rc::try_unwrap(head_node)
-                .ok()
                 // Something else has a reference to the head node.
-                .expect("Something is terribly wrong")
+                .unwrap_or_else(|_| panic!("Something is terribly wrong"))
                 // Remove the `RefCell`.
                 .into_inner()
                 .value
         })
     }
 
-    pub fn iter(&self) -> ListIterator {
+    /// Detach the head node and return it, see [`pop`].
+    ///
+    /// [`pop`]: TransactionLog::pop
+    fn take_front(&mut self) -> Link<T> {
+        let head_node = self.head.take()?;
+        // There is a head node, we borrow it and take the next node assigning
+        // it to the head field of `TransactionLog`. Note we first assign the
+        // `prev` field of the next node to None, since the node being pointed
+        // to by `prev` is being detached.
+        let next_node = head_node.borrow_mut().next.take();
+        if let Some(next_node) = next_node {
+            next_node.borrow_mut().prev = None;
+            self.head = Some(next_node);
+        // There is no next node, remove the `TransactionLog` tail as well to
+        // create an empty `TransactionLog`.
+        } else {
+            self.tail.take();
+        }
+        self.length -= 1;
+        self.checksum_out(&head_node.borrow().value);
+        Some(head_node)
+    }
+
+    /// Pop a value from the back of the `TransactionLog`.
+    pub fn pop_back(&mut self) -> Option<T> {
+        self.tail.take().map(|tail_node| {
+            // There is a tail node, detach it from the node before it which
+            // becomes the new tail.
+            if let Some(prev_node) = tail_node.borrow_mut().take_prev() {
+                prev_node.borrow_mut().next = None;
+                self.tail = Some(prev_node);
+            // There is no previous node, this was the only node so remove the
+            // head as well to create an empty `TransactionLog`.
+            } else {
+                self.head.take();
+            }
+            self.length -= 1;
+            let value = Rc::try_unwrap(tail_node)
+                // Something else has a reference to the tail node.
+                .unwrap_or_else(|_| panic!("Something is terribly wrong"))
+                .into_inner()
+                .value;
+            self.checksum_out(&value);
+            value
+        })
+    }
+
+    /// Remove every entry from the `TransactionLog`, along with anything that
+    /// could be redone.
+    pub fn clear(&mut self) {
+        self.redo.clear();
+        // Dropping the head would drop its `next`, which drops its `next` and
+        // so on, recursing once per node. Break the chain one link at a time
+        // instead so each node is dropped on its own.
+        self.tail.take();
+        let mut current = self.head.take();
+        while let Some(node) = current {
+            current = node.borrow_mut().next.take();
+        }
+        self.length = 0;
+        if let Some(mut checksum) = self.checksum.get() {
+            checksum.sum = 0;
+            self.checksum.set(Some(checksum));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    pub fn iter(&self) -> ListIterator<T> {
         ListIterator::new(self.head.clone())
     }
 
-    pub fn back_iter(&self) -> ListIterator {
+    pub fn back_iter(&self) -> ListIterator<T> {
         ListIterator::new(self.tail.clone())
     }
-}
 
-pub struct ListIterator {
-    // Saves a reference to the current node.
-    current_link: Link,
-}
+    /// Like [`iter`], but also yields each value's index counting from the
+    /// head. See [`IndexedListIterator`] for mixing directions.
+    ///
+    /// [`iter`]: TransactionLog::iter
+    pub fn indexed_iter(&self) -> IndexedListIterator<T> {
+        IndexedListIterator {
+            values: self.iter(),
+            index: 0,
+        }
+    }
 
-impl ListIterator {
-    fn new(start_at: Link) -> ListIterator {
-        ListIterator {
-            current_link: start_at,
+    /// Like [`back_iter`], but also yields each value's index counting from
+    /// the head.
+    ///
+    /// [`back_iter`]: TransactionLog::back_iter
+    pub fn indexed_back_iter(&self) -> IndexedListIterator<T> {
+        IndexedListIterator {
+            values: self.back_iter(),
+            index: self.length.saturating_sub(1),
         }
     }
-}
 
-impl Iterator for ListIterator {
-    type Item = String;
+    /// Consume the `TransactionLog`, yielding owned values from tail to head.
+    pub fn into_iter_rev(self) -> IntoIterRev<T> {
+        IntoIterRev { log: self }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        let current_link = &self.current_link;
-        let mut result = None;
-        self.current_link = match current_link {
-            // There is a current node.
-            Some(current) => {
-                let current_node = current.borrow();
-                // Grab the current node value and return it as the result.
-                result = Some(current_node.value.clone());
-                // Update the `ListIterator.current_link` field with the next
-                // node in the list.
-                current_node.next.clone()
+    /// Remove every entry for which `f` returns `true` and return them as a
+    /// new `TransactionLog`. Both logs keep their original relative order.
+    pub fn extract_matching<F: FnMut(&T) -> bool>(&mut self, mut f: F) -> TransactionLog<T> {
+        let mut extracted = TransactionLog::new();
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            current = node.borrow().next.clone();
+            let matches = f(&node.borrow().value);
+            if matches {
+                extracted.append(self.unlink(node));
             }
-            // There is no current node. We're done iterating.
-            None => None,
+        }
+        extracted
+    }
+
+    /// Detach `node` from the `TransactionLog`, linking its neighbours to each
+    /// other, and return its value.
+    fn unlink(&mut self, node: Rc<RefCell<Node<T>>>) -> T {
+        let prev = node.borrow_mut().take_prev();
+        let next = node.borrow_mut().next.take();
+        // A node without a `prev` is the head, one without a `next` is the
+        // tail. Either way the field pointing at `node` gets overwritten.
+        match &prev {
+            Some(prev_node) => prev_node.borrow_mut().next = next.clone(),
+            None => self.head = next.clone(),
+        }
+        match &next {
+            Some(next_node) => next_node.borrow_mut().prev = prev.as_ref().map(Rc::downgrade),
+            None => self.tail = prev,
+        }
+        self.length -= 1;
+        let value = Rc::try_unwrap(node)
+            // Something else has a reference to the node.
+            .unwrap_or_else(|_| panic!("Something is terribly wrong"))
+            .into_inner()
+            .value;
+        self.checksum_out(&value);
+        value
+    }
+
+    /// Fold `value`, which is being added, into the running checksum.
+    fn checksum_in(&self, value: &T) {
+        if let Some(mut checksum) = self.checksum.get() {
+            checksum.sum = checksum.sum.wrapping_add((checksum.hash)(value));
+            self.checksum.set(Some(checksum));
+        }
+    }
+
+    /// Take `value`, which is being removed, back out of the running checksum.
+    fn checksum_out(&self, value: &T) {
+        if let Some(mut checksum) = self.checksum.get() {
+            checksum.sum = checksum.sum.wrapping_sub((checksum.hash)(value));
+            self.checksum.set(Some(checksum));
+        }
+    }
+
+    /// Keep only the entries for which `f` returns `true`, visiting them from
+    /// head to tail.
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            current = node.borrow().next.clone();
+            let keep = f(&node.borrow().value);
+            if !keep {
+                self.unlink(node);
+            }
+        }
+    }
+
+    /// Reverse the order of the entries in place by swapping every node's
+    /// `prev` and `next` links.
+    pub fn reverse(&mut self) {
+        // Each node's old `prev` becomes its `next`, which has to be a strong
+        // link. Hold on to the node just flipped until its successor has taken
+        // it over, since nothing else owns it in between.
+        let mut flipped: Link<T> = None;
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            current = node.borrow_mut().next.take();
+            {
+                let mut node = node.borrow_mut();
+                node.prev = current.as_ref().map(Rc::downgrade);
+                node.next = flipped.take();
+            }
+            flipped = Some(node);
+        }
+        std::mem::swap(&mut self.head, &mut self.tail);
+    }
+
+    /// Append `value` unless the `TransactionLog` already holds `max` entries,
+    /// in which case the log is left untouched and `value` is handed back in
+    /// the error.
+    pub fn append_capped(&mut self, value: T, max: usize) -> Result<(), CapacityError<T>> {
+        if self.length >= max {
+            return Err(CapacityError { value, max });
+        }
+        self.append(value);
+        Ok(())
+    }
+
+    /// Return the most recent error hit while writing to the `tee` writer.
+    pub fn last_tee_error(&self) -> Option<&io::Error> {
+        self.tee.as_ref().and_then(|tee| tee.last_error.as_ref())
+    }
+
+    /// Move entries from the head to the spill file until at most the spill
+    /// threshold are left in memory. An entry whose write fails stays in
+    /// memory.
+    fn spill_overflow(&mut self) {
+        if self.deferring > 0 {
+            return;
+        }
+        let Some(mut spill) = self.spill.take() else {
+            return;
         };
+        while self.length > spill.threshold {
+            let head = self.head.as_ref().expect("Something is terribly wrong");
+            if !spill.write(&head.borrow().value) {
+                break;
+            }
+            self.pop();
+        }
+        self.spill = Some(spill);
+    }
 
-        result
+    /// Return the most recent error hit while writing to the spill file.
+    pub fn last_spill_error(&self) -> Option<&io::Error> {
+        self.spill
+            .as_ref()
+            .and_then(|spill| spill.last_error.as_ref())
     }
-}
 
-impl DoubleEndedIterator for ListIterator {
-    fn next_back(&mut self) -> Option<Self::Item> {
-        let current_link = &self.current_link;
-        let mut result = None;
-        // There is a current node.
-        self.current_link = match current_link {
-            Some(current) => {
-                let current_node = current.borrow();
-                result = Some(current_node.value.clone());
-                // Update the `ListIterator.current_link` field with the
-                // previous node in the list.
-                current_node.prev.clone()
+    /// Return the sequence id of every entry from head to tail. Ids are handed
+    /// out in increasing order starting at 1 as values are appended. Entries
+    /// moved in by [`paste`] keep the ids of the log they came from.
+    ///
+    /// [`paste`]: TransactionLog::paste
+    pub fn sequence_ids(&self) -> Vec<u64> {
+        let mut ids = Vec::with_capacity(self.length);
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            ids.push(node.borrow().id);
+            current = node.borrow().next.clone();
+        }
+        ids
+    }
+
+    /// Set the sequence id the next appended entry gets. After restoring a log
+    /// this should be one past the highest restored id so new ids don't
+    /// collide with old ones.
+    pub fn reseed_sequence(&mut self, next_id: u64) {
+        self.next_id = next_id;
+    }
+
+    /// Remove the entries in `start..end` and return them as a new
+    /// `TransactionLog`, splicing the surrounding nodes back together. An
+    /// out-of-range or reversed range removes nothing and returns an empty
+    /// log. The removed entries keep their sequence ids, and the new log
+    /// carries on handing out ids from where this one is.
+    pub fn cut(&mut self, start: usize, end: usize) -> TransactionLog<T> {
+        let mut removed = TransactionLog::new();
+        removed.next_id = self.next_id;
+        if start >= end || end > self.length {
+            return removed;
+        }
+        let first = self.node_at(start).expect("Checked against length");
+        let last = self.node_at(end - 1).expect("Checked against length");
+        // Detach the `first..=last` chain and link whatever surrounds it to
+        // each other, which leaves `first` and `last` owned only by us.
+        let before = first.borrow_mut().take_prev();
+        let after = last.borrow_mut().next.take();
+        match &before {
+            Some(before_node) => before_node.borrow_mut().next = after.clone(),
+            None => self.head = after.clone(),
+        }
+        match &after {
+            Some(after_node) => after_node.borrow_mut().prev = before.as_ref().map(Rc::downgrade),
+            None => self.tail = before,
+        }
+        self.length -= end - start;
+        self.checksum.set(None);
+        removed.head = Some(first);
+        removed.tail = Some(last);
+        removed.length = end - start;
+        removed
+    }
+
+    /// Split the `TransactionLog` in two at `index`, leaving the first `index`
+    /// entries and returning a new log with the rest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > length`.
+    pub fn split_off(&mut self, index: usize) -> TransactionLog<T> {
+        assert!(
+            index <= self.length,
+            "split_off index (is {}) should be <= length (is {})",
+            index,
+            self.length
+        );
+        self.cut(index, self.length)
+    }
+
+    /// Return whether any entry equals `value`.
+    pub fn contains<Q>(&self, value: &Q) -> bool
+    where
+        T: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        self.position(value).is_some()
+    }
+
+    /// Return the index, counting from the head, of the first entry equal to
+    /// `value`.
+    pub fn position<Q>(&self, value: &Q) -> Option<usize>
+    where
+        T: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        let mut index = 0;
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            if borrows_eq(&node.borrow().value, value) {
+                return Some(index);
             }
-            // There is no current node. We're done iterating.
-            None => None,
+            index += 1;
+            current = node.borrow().next.clone();
+        }
+        None
+    }
+
+    /// Move the values out into a `Vec`, head first.
+    pub fn into_vec(self) -> Vec<T> {
+        self.into_iter().collect()
+    }
+
+    /// Return the node at `index`, counting from the head. Indexes in the
+    /// back half are walked to from the tail instead.
+    fn node_at(&self, index: usize) -> Link<T> {
+        if index >= self.length {
+            return None;
+        }
+        if index > self.length / 2 {
+            let mut current = self.tail.clone();
+            for _ in index + 1..self.length {
+                current = current?.borrow().prev.as_ref().and_then(Weak::upgrade);
+            }
+            return current;
+        }
+        let mut current = self.head.clone();
+        for _ in 0..index {
+            current = current?.borrow().next.clone();
+        }
+        current
+    }
+
+    /// Replace the value at `index`, counting from the head, returning the old
+    /// one. This counts as an edit, bumping the entry's generation.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index >= length`.
+    pub fn replace_at(&mut self, index: usize, value: T) -> T {
+        assert!(
+            index < self.length,
+            "replace index (is {}) should be < length (is {})",
+            index,
+            self.length
+        );
+        self.checksum_in(&value);
+        let node = self.node_at(index).expect("Something is terribly wrong");
+        let mut node = node.borrow_mut();
+        node.generation += 1;
+        let old = std::mem::replace(&mut node.value, value);
+        self.checksum_out(&old);
+        old
+    }
+
+    /// Splice all of `other`'s nodes into the `TransactionLog` so they start
+    /// at `index`. Pasting at 0 prepends, pasting at `length` appends.
+    ///
+    /// Pasted entries keep the sequence ids `other` gave them, so ids are
+    /// only unique within the log that handed them out. Later appends get ids
+    /// past those of both logs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > length`.
+    pub fn paste(&mut self, index: usize, mut other: TransactionLog<T>) {
+        assert!(
+            index <= self.length,
+            "paste index (is {}) should be <= length (is {})",
+            index,
+            self.length
+        );
+        let (first, last) = match (other.head.take(), other.tail.take()) {
+            (Some(first), Some(last)) => (first, last),
+            // Pasting an empty log changes nothing.
+            _ => return,
+        };
+        let count = other.length;
+        other.length = 0;
+        self.next_id = self.next_id.max(other.next_id);
+        let after = self.node_at(index);
+        self.splice_before(after, first, last, count);
+        self.checksum.set(None);
+    }
+
+    /// Move all of `other`'s entries onto the end of the `TransactionLog` in
+    /// O(1), relinking the nodes rather than copying the values.
+    pub fn concat(&mut self, other: TransactionLog<T>) {
+        self.paste(self.length, other);
+    }
+
+    /// Link the chain `first..=last` of `count` nodes in front of `after`, or
+    /// at the tail if `after` is `None`.
+    fn splice_before(
+        &mut self,
+        after: Link<T>,
+        first: Rc<RefCell<Node<T>>>,
+        last: Rc<RefCell<Node<T>>>,
+        count: usize,
+    ) {
+        // The chain goes between `before` and `after`, either of which is
+        // missing when splicing at an end.
+        let before = match &after {
+            Some(after_node) => after_node.borrow_mut().take_prev(),
+            None => self.tail.take(),
         };
+        match &before {
+            Some(before_node) => before_node.borrow_mut().next = Some(first.clone()),
+            None => self.head = Some(first.clone()),
+        }
+        match &after {
+            Some(after_node) => after_node.borrow_mut().prev = Some(Rc::downgrade(&last)),
+            None => self.tail = Some(last.clone()),
+        }
+        first.borrow_mut().prev = before.as_ref().map(Rc::downgrade);
+        last.borrow_mut().next = after;
+        self.length += count;
+        // Let go of the nodes around the splice first, eviction may pop them.
+        drop((before, first, last));
+        self.evict_over_capacity();
+    }
+
+    /// Insert `value` in a new node in front of `after`, or at the tail if
+    /// `after` is `None`.
+    fn insert_value_before(&mut self, after: Link<T>, value: T) {
+        self.checksum_in(&value);
+        let node = Node::new(value, self.next_id);
+        self.next_id += 1;
+        self.splice_before(after, node.clone(), node, 1);
+    }
+
+    /// Return a cursor that can walk the `TransactionLog` and edit it in the
+    /// middle, starting at the head.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, T> {
+        CursorMut {
+            current: self.head.as_ref().map(Rc::downgrade),
+            log: self,
+        }
+    }
+
+    /// Run `f` against the `TransactionLog`, keeping its changes if it returns
+    /// `Ok`. On `Err` entries are popped from the back until the log is back
+    /// to the length it had before `f` ran, then the error is passed on.
+    ///
+    /// The rollback only pops from the back until the old length is reached,
+    /// it doesn't check which entries it pops. If `f` removed entries as well
+    /// as appending, the log ends up with the old length but not the old
+    /// entries.
+    pub fn with_savepoint<R, E, F: FnOnce(&mut TransactionLog<T>) -> Result<R, E>>(
+        &mut self,
+        f: F,
+    ) -> Result<R, E> {
+        let savepoint = self.length;
+        self.deferring(|log| {
+            let result = f(log);
+            if result.is_err() {
+                while log.length > savepoint {
+                    log.pop_back();
+                }
+            }
+            result
+        })
+    }
 
+    /// Run `f` with eviction and spilling held off, so every entry it appends
+    /// or rolls back over stays in memory where a rollback can still reach
+    /// it, and a delta sees the entries it was made against. Whatever is over
+    /// the cap or the spill threshold goes once the outermost call is done.
+    /// Savepoints, scripts and deltas all run through here.
+    fn deferring<R, F: FnOnce(&mut TransactionLog<T>) -> R>(&mut self, f: F) -> R {
+        self.deferring += 1;
+        let result = f(self);
+        self.deferring -= 1;
+        self.evict_over_capacity();
+        self.spill_overflow();
         result
     }
+
+    /// Mark the current end of the `TransactionLog`, see [`rollback_to`].
+    ///
+    /// [`rollback_to`]: TransactionLog::rollback_to
+    pub fn checkpoint(&self) -> Checkpoint<T> {
+        Checkpoint {
+            head: self.head.as_ref().map(Rc::downgrade),
+            tail: self.tail.as_ref().map(Rc::downgrade),
+            length: self.length,
+        }
+    }
+
+    /// Pop entries from the back until the log is back to what it held when
+    /// `cp` was taken. Fails, leaving the log untouched, if any entry up to
+    /// the checkpoint has been removed since: the head and the checkpointed
+    /// tail must still be in place, with as many entries between them as
+    /// before.
+    pub fn rollback_to(&mut self, cp: Checkpoint<T>) -> Result<(), CheckpointError> {
+        // Check everything before popping any, so a checkpoint that's gone
+        // doesn't lose anything.
+        if let (Some(head), Some(tail)) = (cp.head, cp.tail) {
+            let head = head.upgrade().ok_or(CheckpointError)?;
+            let tail = tail.upgrade().ok_or(CheckpointError)?;
+            match &self.head {
+                Some(node) if Rc::ptr_eq(node, &head) => {}
+                _ => return Err(CheckpointError),
+            }
+            let mut current = head;
+            for _ in 1..cp.length {
+                let next = current.borrow().next.clone().ok_or(CheckpointError)?;
+                current = next;
+            }
+            if !Rc::ptr_eq(&current, &tail) {
+                return Err(CheckpointError);
+            }
+        }
+        for _ in cp.length..self.length {
+            self.pop_back();
+        }
+        Ok(())
+    }
+
+    /// Append `value` only if `gate` returns `true`, returning whether it was
+    /// appended.
+    pub fn append_if<F: FnOnce() -> bool>(&mut self, value: T, gate: F) -> bool {
+        let open = gate();
+        if open {
+            self.append(value);
+        }
+        open
+    }
+
+    /// Keep only the entries at indices `0, n, 2n, ...` and remove the rest.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn keep_every(&mut self, n: usize) {
+        assert!(n > 0, "keep_every step must be non-zero");
+        let mut index = 0;
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            current = node.borrow().next.clone();
+            if index % n != 0 {
+                self.unlink(node);
+            }
+            index += 1;
+        }
+    }
+
+    /// Count the nodes reachable from the head by following `next` links,
+    /// independently of `length`. A node seen twice means the links loop, the
+    /// walk stops there so every node is only counted once.
+    pub fn reachable_count(&self) -> usize {
+        let mut seen = HashSet::new();
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            if !seen.insert(Rc::as_ptr(&node)) {
+                break;
+            }
+            current = node.borrow().next.clone();
+        }
+        seen.len()
+    }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+impl<T: Clone> TransactionLog<T> {
+    /// Return a copy of the value at `index`, counting from the head.
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.node_at(index).map(|node| node.borrow().value.clone())
+    }
 
-    #[test]
-    fn new_transaction_log_can_be_created() {
-        let tl = TransactionLog::new();
+    /// Take a snapshot of the values for repeated random access, see
+    /// [`IndexedView`].
+    pub fn as_indexed(&self) -> IndexedView<T> {
+        IndexedView {
+            values: self.iter().collect(),
+        }
+    }
 
-        assert_eq!(tl.length, 0);
+    /// Copy the values into a `Vec`, head first.
+    pub fn to_vec(&self) -> Vec<T> {
+        self.iter().collect()
     }
 
-    #[test]
-    fn items_can_be_appended_and_popped_from_transaction_log() {
-        let mut tl = TransactionLog::new();
-        tl.append("Log Item 1".to_string());
-        tl.append("Log Item 2".to_string());
-        tl.append("Log Item 3".to_string());
+    /// Return a copy of the value at the head without removing it.
+    pub fn peek(&self) -> Option<T> {
+        self.head.as_ref().map(|head| head.borrow().value.clone())
+    }
 
-        assert_eq!(tl.length, 3);
-        assert_eq!(tl.pop(), Some("Log Item 1".to_string()));
-        assert_eq!(tl.pop(), Some("Log Item 2".to_string()));
-        assert_eq!(tl.pop(), Some("Log Item 3".to_string()));
-        assert_eq!(tl.pop(), None);
+    /// Return a copy of the value at the tail without removing it.
+    pub fn peek_back(&self) -> Option<T> {
+        self.tail.as_ref().map(|tail| tail.borrow().value.clone())
     }
 
-    #[test]
-    fn transaction_log_can_be_forward_iterated() {
-        let mut tl = TransactionLog::new();
-        tl.append("Log Item 1".to_string());
-        tl.append("Log Item 2".to_string());
-        tl.append("Log Item 3".to_string());
+    /// Iterate over every value along with its generation, the number of times
+    /// it has been edited in place, from head to tail. A changed generation
+    /// means the entry changed since it was last read.
+    pub fn iter_versioned(&self) -> impl Iterator<Item = (T, u64)> {
+        let mut current = self.head.clone();
+        std::iter::from_fn(move || {
+            let node = current.take()?;
+            let node = node.borrow();
+            current = node.next.clone();
+            Some((node.value.clone(), node.generation))
+        })
+    }
 
-        assert_eq!(tl.iter().count(), 3);
-        for t in tl.iter().zip([
-            "Log Item 1".to_string(),
-            "Log Item 2".to_string(),
-            "Log Item 3".to_string(),
-        ]) {
-            assert_eq!(t.0, t.1)
+    /// Take back the most recent entry, returning a copy of it. It can be put
+    /// back with [`redo`].
+    ///
+    /// Only `append` and `clear` drop what could be redone, along with the
+    /// methods that append through `append`: `append_if`, `append_capped`,
+    /// `append_repeated`, `append_log` and `extend`. A `run_script` script
+    /// drops it with an `append` or `clear` command, and gets it back if the
+    /// script fails. A failed `with_savepoint` doesn't bring back what its
+    /// closure dropped. Every other edit, such as `pop`, `append_front`,
+    /// `paste` or a cursor edit, keeps it, and `redo` puts the entry back at
+    /// the tail of whatever the log holds then.
+    ///
+    /// [`redo`]: TransactionLog::redo
+    pub fn undo(&mut self) -> Option<T> {
+        let value = self.pop_back()?;
+        self.redo.push(value.clone());
+        Some(value)
+    }
+
+    /// Put back the entry most recently taken back by [`undo`], returning a
+    /// copy of it.
+    ///
+    /// [`undo`]: TransactionLog::undo
+    pub fn redo(&mut self) -> Option<T> {
+        let value = self.redo.pop()?;
+        self.push_back(value.clone());
+        Some(value)
+    }
+
+    /// Sum the scores `f` assigns to each entry. An empty log sums to `0.0`.
+    pub fn weighted_sum<F: FnMut(&T) -> f64>(&self, mut f: F) -> f64 {
+        self.iter().map(|value| f(&value)).sum()
+    }
+
+    /// Slide a window of `window` entries across the `TransactionLog`,
+    /// advancing by `step` entries at a time, and collect `f` applied to each
+    /// window into a new `TransactionLog`. A trailing window that would run
+    /// past the end of the log is dropped rather than truncated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` or `step` is zero.
+    pub fn reduce_windows<F: Fn(&[T]) -> T>(
+        &self,
+        window: usize,
+        step: usize,
+        f: F,
+    ) -> TransactionLog<T> {
+        assert!(window > 0, "window size must be non-zero");
+        assert!(step > 0, "step must be non-zero");
+        let values: Vec<T> = self.iter().collect();
+        let mut reduced = TransactionLog::new();
+        for w in values.windows(window).step_by(step) {
+            reduced.append(f(w));
         }
+        reduced
     }
 
-    #[test]
-    fn transaction_log_can_be_backward_iterated() {
+    /// Return the entry in the middle of the `TransactionLog` by position, at
+    /// index `length / 2`.
+    pub fn median_entry(&self) -> Option<T> {
+        self.iter().nth(self.length / 2)
+    }
+
+    /// Rebuild a `TransactionLog` by applying `ops` in order to an empty log.
+    pub fn replay_ops(ops: &[LogOp<T>]) -> TransactionLog<T> {
         let mut tl = TransactionLog::new();
-        tl.append("Log Item 1".to_string());
-        tl.append("Log Item 2".to_string());
-        tl.append("Log Item 3".to_string());
+        for op in ops {
+            match op {
+                LogOp::Append(value) => tl.append(value.clone()),
+                LogOp::Pop => {
+                    tl.pop();
+                }
+                LogOp::AppendFront(value) => tl.append_front(value.clone()),
+                LogOp::Clear => tl.clear(),
+            }
+        }
+        tl
+    }
 
-        assert_eq!(tl.back_iter().rev().count(), 3);
-        for t in tl.back_iter().rev().zip([
-            "Log Item 3".to_string(),
-            "Log Item 2".to_string(),
-            "Log Item 1".to_string(),
-        ]) {
-            assert_eq!(t.0, t.1)
+    /// Iterate over every adjacent pair of values from head to tail.
+    pub fn pairwise(&self) -> impl Iterator<Item = (T, T)> {
+        self.iter().zip(self.iter().skip(1))
+    }
+
+    /// Append `count` copies of `value`.
+    pub fn append_repeated(&mut self, value: T, count: usize) {
+        for _ in 0..count {
+            self.append(value.clone());
+        }
+    }
+
+    /// Return a new `TransactionLog` where entry `i` is the lexically largest
+    /// value among entries `0..=i`.
+    pub fn running_max(&self) -> TransactionLog<T>
+    where
+        T: Ord,
+    {
+        let mut maxima = TransactionLog::new();
+        let mut max: Option<T> = None;
+        for value in self.iter() {
+            let current = match max.take() {
+                Some(m) if m >= value => m,
+                _ => value,
+            };
+            maxima.append(current.clone());
+            max = Some(current);
+        }
+        maxima
+    }
+
+    /// Return a new `TransactionLog` with the same values in a pseudo-random
+    /// order determined by `seed`. The same seed always gives the same order.
+    pub fn shuffled(&self, seed: u64) -> TransactionLog<T> {
+        let mut values: Vec<T> = self.iter().collect();
+        // xorshift64 gets stuck on a zero state, so the one seed that mixes
+        // to zero starts from the same state as seed 0 instead.
+        const MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut state = match seed ^ MIX {
+            0 => MIX,
+            state => state,
+        };
+        // Fisher-Yates, drawing swap positions from the xorshift64 generator.
+        for i in (1..values.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            values.swap(i, (state % (i as u64 + 1)) as usize);
+        }
+        let mut shuffled = TransactionLog::new();
+        for value in values {
+            shuffled.append(value);
+        }
+        shuffled
+    }
+
+    /// Split the `TransactionLog` into groups that each end with an entry for
+    /// which `f` returns `true`, keeping that entry as the last of its group.
+    /// Entries after the last such entry form a final group, as with
+    /// `slice::split_inclusive`.
+    pub fn split_inclusive<F: FnMut(&T) -> bool>(&self, mut f: F) -> Vec<TransactionLog<T>> {
+        let mut groups = Vec::new();
+        let mut group = TransactionLog::new();
+        for value in self.iter() {
+            let ends_group = f(&value);
+            group.append(value);
+            if ends_group {
+                groups.push(std::mem::take(&mut group));
+            }
+        }
+        if group.length > 0 {
+            groups.push(group);
         }
+        groups
+    }
+
+    /// Return the most recently appended value for which `f` returns `true`,
+    /// walking from the tail.
+    pub fn last_matching<F: FnMut(&T) -> bool>(&self, mut f: F) -> Option<T> {
+        self.back_iter().rev().find(|value| f(value))
+    }
+
+    /// Group entries into logical records, each made of a starting entry and
+    /// every following entry for which `is_continuation` returns `true`. The
+    /// first entry always starts a record, even if it looks like a
+    /// continuation.
+    pub fn records<F: FnMut(&T) -> bool>(&self, is_continuation: F) -> Records<T, F> {
+        Records {
+            values: self.iter().peekable(),
+            is_continuation,
+        }
+    }
+
+    /// Turn the `TransactionLog` into current state by mapping every entry to
+    /// a key-value pair with `key_val`, later entries overwriting earlier ones
+    /// with the same key.
+    pub fn materialize<K: Eq + Hash, V, F: FnMut(&T) -> (K, V)>(
+        &self,
+        mut key_val: F,
+    ) -> HashMap<K, V> {
+        self.iter().map(|value| key_val(&value)).collect()
+    }
+
+    /// Iterate like [`iter`] but stop after at most `max_steps` values, even
+    /// if the `next` links carry on, e.g. because a corrupt log has a cycle.
+    ///
+    /// [`iter`]: TransactionLog::iter
+    pub fn iter_capped(&self, max_steps: usize) -> Take<ListIterator<T>> {
+        self.iter().take(max_steps)
+    }
+}
+
+impl<T: Clone + PartialEq> TransactionLog<T> {
+    /// Rotate the `TransactionLog` left until the first node holding `value`
+    /// becomes the head, treating the log as circular. Returns `false` and
+    /// leaves the log unchanged if `value` isn't present.
+    pub fn rotate_to<Q>(&mut self, value: &Q) -> bool
+    where
+        T: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            if borrows_eq(&node.borrow().value, value) {
+                // The matching node becomes the new head, so the node before
+                // it becomes the new tail. No `prev` means it already is the
+                // head and there is nothing to rotate.
+                let new_tail = match node.borrow_mut().take_prev() {
+                    Some(new_tail) => new_tail,
+                    None => return true,
+                };
+                new_tail.borrow_mut().next = None;
+                // Close the circle by linking the old tail to the old head.
+                let old_head = self.head.take().expect("Non-empty log has a head");
+                let old_tail = self.tail.take().expect("Non-empty log has a tail");
+                old_head.borrow_mut().prev = Some(Rc::downgrade(&old_tail));
+                old_tail.borrow_mut().next = Some(old_head);
+                self.head = Some(node);
+                self.tail = Some(new_tail);
+                return true;
+            }
+            current = node.borrow().next.clone();
+        }
+        false
+    }
+
+    /// Return whether both logs agree on their first `n` entries. A log
+    /// shorter than `n` only agrees with one of the same length.
+    pub fn eq_prefix(&self, other: &TransactionLog<T>, n: usize) -> bool {
+        self.iter().take(n).eq(other.iter().take(n))
+    }
+
+    /// Return whether the values of `self` appear in `other` in the same
+    /// relative order, though not necessarily next to each other.
+    pub fn is_subsequence_of(&self, other: &TransactionLog<T>) -> bool {
+        let mut haystack = other.iter();
+        self.iter()
+            .all(|needle| haystack.any(|value| value == needle))
+    }
+
+    /// Collapse every `A, B, A` oscillation, where `B` differs from `A`, down
+    /// to the first `A` and return how many collapses happened.
+    ///
+    /// The log is scanned from the head. After a collapse the scan stays on
+    /// the kept `A`, so `[a, b, a, b, a]` collapses twice down to `[a]`.
+    pub fn collapse_oscillations(&mut self) -> usize {
+        let mut collapses = 0;
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let middle = node.borrow().next.clone();
+            let third = middle.as_ref().and_then(|m| m.borrow().next.clone());
+            if let (Some(middle), Some(third)) = (middle, third) {
+                let oscillates = {
+                    let value = &node.borrow().value;
+                    *value == third.borrow().value && *value != middle.borrow().value
+                };
+                if oscillates {
+                    self.unlink(middle);
+                    self.unlink(third);
+                    collapses += 1;
+                    current = Some(node);
+                    continue;
+                }
+            }
+            current = node.borrow().next.clone();
+        }
+        collapses
+    }
+
+    /// Return the value following the first entry equal to `value`, or `None`
+    /// if there is no such entry or it is the tail.
+    pub fn value_after<Q>(&self, value: &Q) -> Option<T>
+    where
+        T: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        let mut values = self.iter();
+        values.find(|v| borrows_eq(v, value))?;
+        values.next()
+    }
+
+    /// Return whether the values read the same from head to tail as from tail
+    /// to head. Empty and single-entry logs are palindromes.
+    pub fn is_palindrome(&self) -> bool {
+        let mut front = self.head.clone();
+        let mut back = self.tail.clone();
+        // Walk both cursors towards the middle, comparing as they go.
+        for _ in 0..self.length / 2 {
+            let front_node = front.expect("Cursor stays within the log");
+            let back_node = back.expect("Cursor stays within the log");
+            if front_node.borrow().value != back_node.borrow().value {
+                return false;
+            }
+            front = front_node.borrow().next.clone();
+            back = back_node.borrow().prev.as_ref().and_then(Weak::upgrade);
+        }
+        true
+    }
+
+    /// Remove the runs of entries equal to `value` from both ends of the
+    /// `TransactionLog`, returning how many were removed. Matching entries in
+    /// between are left alone.
+    pub fn trim_matches<Q>(&mut self, value: &Q) -> usize
+    where
+        T: std::borrow::Borrow<Q>,
+        Q: PartialEq + ?Sized,
+    {
+        let start = self.length;
+        while self
+            .head
+            .as_ref()
+            .is_some_and(|n| borrows_eq(&n.borrow().value, value))
+        {
+            self.pop();
+        }
+        while self
+            .tail
+            .as_ref()
+            .is_some_and(|n| borrows_eq(&n.borrow().value, value))
+        {
+            self.pop_back();
+        }
+        start - self.length
+    }
+
+    /// Return the `(start, end)` bounds of the longest run of consecutive
+    /// entries, `start..end`, whose values read the same in both directions.
+    /// Ties go to the earliest run.
+    pub fn longest_palindrome_range(&self) -> Option<(usize, usize)> {
+        let values: Vec<T> = self.iter().collect();
+        let mut longest: Option<(usize, usize)> = None;
+        // Expand around every centre, both on an entry (odd lengths) and
+        // between two entries (even lengths), in order of position.
+        for centre in 0..2 * values.len() {
+            let (mut start, mut end) = (centre / 2, centre / 2 + centre % 2);
+            while start > 0 && end < values.len() && values[start - 1] == values[end] {
+                start -= 1;
+                end += 1;
+            }
+            // An even centre that can't expand yields an empty run, skip it.
+            let length = end - start;
+            if length > 0 && longest.is_none_or(|(s, e)| length > e - s) {
+                longest = Some((start, end));
+            }
+        }
+        longest
+    }
+}
+
+impl<T: Clone + Eq + Hash> TransactionLog<T> {
+    /// Pop values from the front of the `TransactionLog` until it holds at
+    /// most `max_distinct` distinct values.
+    pub fn keep_distinct(&mut self, max_distinct: usize) {
+        let mut counts = self.value_counts();
+        while counts.len() > max_distinct {
+            let value = self.pop().expect("Distinct values imply a non-empty log");
+            // Forget the value once its last occurrence has been evicted.
+            if let Some(count) = counts.get_mut(&value) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&value);
+                }
+            }
+        }
+    }
+
+    /// Count the occurrences of every distinct value.
+    fn value_counts(&self) -> HashMap<T, usize> {
+        let mut counts = HashMap::new();
+        for value in self.iter() {
+            *counts.entry(value).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Return the value that occurs most often. Ties go to the value that
+    /// reaches the highest count first.
+    pub fn mode(&self) -> Option<T> {
+        let mut counts = HashMap::new();
+        let mut mode: Option<(T, usize)> = None;
+        for value in self.iter() {
+            let count = counts.entry(value.clone()).or_insert(0);
+            *count += 1;
+            // Only a strictly higher count takes over, so a tie never
+            // replaces the value that got there first.
+            if mode.as_ref().is_none_or(|(_, best)| *count > *best) {
+                mode = Some((value, *count));
+            }
+        }
+        mode.map(|(value, _)| value)
+    }
+
+    /// Return the distinct values present in `self` but not in `other`, in the
+    /// order they first appear in `self`.
+    pub fn values_only_in(&self, other: &TransactionLog<T>) -> Vec<T> {
+        let mut seen: HashSet<T> = other.iter().collect();
+        // Values are added to `seen` as they're taken, which dedups them.
+        self.iter()
+            .filter(|value| seen.insert(value.clone()))
+            .collect()
+    }
+
+    /// Return every distinct value with its number of occurrences, most
+    /// frequent first and ties ordered by value.
+    pub fn by_frequency(&self) -> Vec<(T, usize)>
+    where
+        T: Ord,
+    {
+        let mut frequencies: Vec<(T, usize)> = self.value_counts().into_iter().collect();
+        frequencies
+            .sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+        frequencies
+    }
+
+    /// Shannon entropy, in bits, of the distribution of values. A log of
+    /// identical values has an entropy of `0.0`, as does an empty log.
+    pub fn shannon_entropy(&self) -> f64 {
+        let total = self.length as f64;
+        self.value_counts()
+            .values()
+            .map(|&count| {
+                let p = count as f64 / total;
+                -p * p.log2()
+            })
+            .sum()
+    }
+
+    /// Map every distinct value to the indices of its first and last
+    /// occurrence.
+    pub fn value_spans(&self) -> HashMap<T, (usize, usize)> {
+        let mut spans = HashMap::new();
+        for (index, value) in self.iter().enumerate() {
+            spans
+                .entry(value)
+                .and_modify(|(_, last)| *last = index)
+                .or_insert((index, index));
+        }
+        spans
+    }
+
+    /// Return the distinct values present in exactly one of the two logs,
+    /// those only in `self` first in `self`'s order, then those only in
+    /// `other` in `other`'s order.
+    pub fn symmetric_difference(&self, other: &TransactionLog<T>) -> Vec<T> {
+        let mut difference = self.values_only_in(other);
+        difference.extend(other.values_only_in(self));
+        difference
+    }
+}
+
+impl<T: Hash> TransactionLog<T> {
+    /// Return a checksum of the values in the `TransactionLog`. It's kept up
+    /// to date as entries are appended and popped, so after the first call it
+    /// costs O(1) until an edit like [`cut`] makes it stale and the next call
+    /// recomputes it.
+    ///
+    /// The checksum is the wrapping sum of every value's hash, so it doesn't
+    /// depend on the order of the entries. Hashes aren't stable across Rust
+    /// releases, only compare checksums computed by the same build.
+    ///
+    /// [`cut`]: TransactionLog::cut
+    pub fn checksum(&self) -> u64 {
+        if let Some(checksum) = self.checksum.get() {
+            return checksum.sum;
+        }
+        let mut sum: u64 = 0;
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            sum = sum.wrapping_add(value_hash(&node.borrow().value));
+            current = node.borrow().next.clone();
+        }
+        self.checksum.set(Some(RunningChecksum {
+            sum,
+            hash: value_hash,
+        }));
+        sum
+    }
+}
+
+impl<T: fmt::Display> TransactionLog<T> {
+    /// Mirror every value appended from now on into `writer`, one line per
+    /// value. Replaces any previously registered writer. Write failures don't
+    /// stop the append, the most recent one is kept for [`last_tee_error`].
+    ///
+    /// [`last_tee_error`]: TransactionLog::last_tee_error
+    pub fn tee<W: Write + 'static>(&mut self, writer: W) {
+        self.tee = Some(Tee {
+            writer: Box::new(writer),
+            write_value: |writer, value| writeln!(writer, "{}", value),
+            last_error: None,
+        });
+    }
+}
+
+impl TransactionLog<String> {
+    /// Keep at most `n` entries in memory. Whenever the log grows past that,
+    /// the oldest entries are written to a new file at `path` and dropped from
+    /// the list, so `length` and the other operations only see what's left in
+    /// memory. Use [`iter_all`] to read the full history back. Calling this
+    /// again starts over with a new, empty file.
+    ///
+    /// Spilling is held off while a savepoint, script or delta is applied.
+    ///
+    /// [`iter_all`]: TransactionLog::iter_all
+    pub fn spill_after<P: AsRef<Path>>(&mut self, n: usize, path: P) -> io::Result<()> {
+        let path = path.as_ref().to_path_buf();
+        self.spill = Some(Spill {
+            file: File::create(&path)?,
+            path,
+            threshold: n,
+            count: 0,
+            bytes: 0,
+            encode: |out, value| {
+                out.extend_from_slice(&(value.len() as u64).to_le_bytes());
+                out.extend_from_slice(value.as_bytes());
+            },
+            last_error: None,
+        });
+        self.spill_overflow();
+        Ok(())
+    }
+
+    /// Iterate over every entry ever spilled by [`spill_after`], oldest first,
+    /// followed by the entries still in memory. Spilled entries are read from
+    /// disk lazily, so each item can fail.
+    ///
+    /// [`spill_after`]: TransactionLog::spill_after
+    pub fn iter_all(&self) -> io::Result<impl Iterator<Item = io::Result<String>>> {
+        let spilled = match &self.spill {
+            Some(spill) => Some(SpilledValues {
+                reader: BufReader::new(File::open(&spill.path)?),
+                remaining: spill.count,
+            }),
+            None => None,
+        };
+        Ok(spilled.into_iter().flatten().chain(self.iter().map(Ok)))
+    }
+
+    /// Return a new `TransactionLog` where every entry after the first is the
+    /// difference from its predecessor, the first entry is carried through
+    /// unchanged. This is the inverse of a running sum. Fails with
+    /// [`ParseError::Overflow`] if a difference doesn't fit in an `i64`.
+    pub fn deltas(&self) -> Result<TransactionLog, ParseError> {
+        let numbers = self.parse_entries()?;
+        let mut deltas = TransactionLog::new();
+        let mut previous = None;
+        for (index, n) in numbers.into_iter().enumerate() {
+            let delta = match previous {
+                Some(p) => n.checked_sub(p).ok_or_else(|| ParseError::Overflow {
+                    index,
+                    value: n.to_string(),
+                })?,
+                None => n,
+            };
+            deltas.append(delta.to_string());
+            previous = Some(n);
+        }
+        Ok(deltas)
+    }
+
+    /// Parse every entry as an integer, failing on the first entry that isn't
+    /// one.
+    fn parse_entries(&self) -> Result<Vec<i64>, ParseError> {
+        self.iter()
+            .enumerate()
+            .map(|(index, value)| {
+                value
+                    .parse()
+                    .map_err(|_| ParseError::NotAnInteger { index, value })
+            })
+            .collect()
+    }
+
+    /// Return whether every entry matches `pattern` in full. The pattern is
+    /// compiled once, a bad pattern returns the compile error.
+    #[cfg(feature = "regex")]
+    pub fn all_match(&self, pattern: &str) -> Result<bool, regex::Error> {
+        // Anchor the whole pattern so a match has to cover the entire entry.
+        let re = regex::Regex::new(&format!("^(?:{})$", pattern))?;
+        Ok(self.iter().all(|value| re.is_match(&value)))
+    }
+
+    /// Return a new `TransactionLog` holding capture group `group` of
+    /// `pattern` from every entry. Entries that don't match, or where the
+    /// group didn't participate in the match, are skipped.
+    #[cfg(feature = "regex")]
+    pub fn extract(&self, pattern: &str, group: usize) -> Result<TransactionLog, regex::Error> {
+        let re = regex::Regex::new(pattern)?;
+        let mut extracted = TransactionLog::new();
+        for value in self.iter() {
+            if let Some(capture) = re.captures(&value).and_then(|c| c.get(group)) {
+                extracted.append(capture.as_str().to_string());
+            }
+        }
+        Ok(extracted)
+    }
+
+    /// Build a `TransactionLog` holding each pair formatted as `"k=v"`.
+    pub fn from_pairs(pairs: &[(String, String)]) -> TransactionLog {
+        let mut tl = TransactionLog::new();
+        for (key, value) in pairs {
+            tl.append(format!("{}={}", key, value));
+        }
+        tl
+    }
+
+    /// Split every entry on its first `=` into a key-value pair, skipping
+    /// entries without one.
+    pub fn parse_pairs(&self) -> Vec<(String, String)> {
+        self.iter()
+            .filter_map(|entry| {
+                entry
+                    .split_once('=')
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+            })
+            .collect()
+    }
+
+    /// Return the entry with the smallest summed character-level Levenshtein
+    /// distance to every other entry, the earliest one on ties.
+    ///
+    /// This compares every pair of entries, so it costs O(n² · m) for `n`
+    /// entries of length `m`.
+    pub fn medoid(&self) -> Option<String> {
+        let values: Vec<String> = self.iter().collect();
+        let mut medoid: Option<(&String, usize)> = None;
+        for candidate in &values {
+            let total = values.iter().map(|v| levenshtein(candidate, v)).sum();
+            if medoid.is_none_or(|(_, best)| total < best) {
+                medoid = Some((candidate, total));
+            }
+        }
+        medoid.map(|(value, _)| value.clone())
+    }
+
+    /// Remove every entry that is empty or only whitespace, returning how many
+    /// were removed.
+    pub fn remove_blank(&mut self) -> usize {
+        let before = self.length;
+        self.retain(|value| !value.trim().is_empty());
+        before - self.length
+    }
+
+    /// Merge runs of consecutive entries into single entries joined by `sep`,
+    /// as long as a merged entry stays within `max_len` characters. Each
+    /// merged entry keeps the sequence id of its first entry, and entries
+    /// already longer than `max_len` are left alone.
+    pub fn merge_short(&mut self, max_len: usize, sep: &str) {
+        let sep_len = sep.chars().count();
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let next = node.borrow().next.clone();
+            current = match next {
+                Some(next_node)
+                    if node.borrow().value.chars().count()
+                        + sep_len
+                        + next_node.borrow().value.chars().count()
+                        <= max_len =>
+                {
+                    let value = self.unlink(next_node);
+                    {
+                        let mut merged = node.borrow_mut();
+                        merged.value.push_str(sep);
+                        merged.value.push_str(&value);
+                        merged.generation += 1;
+                    }
+                    self.checksum.set(None);
+                    // Keep merging into the same entry.
+                    Some(node)
+                }
+                next => next,
+            };
+        }
+    }
+
+    /// Parse every entry as an integer and return the `(start_index, length)`
+    /// of the longest strictly increasing run of consecutive entries, the
+    /// earliest one on ties. An empty log has the run `(0, 0)`.
+    pub fn longest_increasing_run(&self) -> Result<(usize, usize), ParseError> {
+        let numbers = self.parse_entries()?;
+        let mut longest = (0, 0);
+        let mut start = 0;
+        for i in 0..numbers.len() {
+            // A run breaks whenever an entry doesn't exceed its predecessor.
+            if i > 0 && numbers[i] <= numbers[i - 1] {
+                start = i;
+            }
+            let length = i - start + 1;
+            if length > longest.1 {
+                longest = (start, length);
+            }
+        }
+        Ok(longest)
+    }
+
+    /// Return the index and value of the first entry longer than `max_chars`
+    /// characters.
+    pub fn first_exceeding(&self, max_chars: usize) -> Option<(usize, String)> {
+        self.iter()
+            .enumerate()
+            .find(|(_, value)| value.chars().count() > max_chars)
+    }
+
+    /// Return the character length at percentile `p` of the entries'
+    /// lengths, using the nearest-rank method, so `0.5` gives the median.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `p` isn't within `0.0..=1.0`.
+    pub fn length_percentile(&self, p: f64) -> Option<usize> {
+        assert!(
+            (0.0..=1.0).contains(&p),
+            "percentile must be within 0.0..=1.0"
+        );
+        let mut lengths: Vec<usize> = self.iter().map(|value| value.chars().count()).collect();
+        lengths.sort_unstable();
+        // The smallest rank covering at least `p` of the entries, counting
+        // from 1.
+        let rank = (p * lengths.len() as f64).ceil() as usize;
+        lengths.get(rank.max(1) - 1).copied()
+    }
+
+    /// Return every value starting with `prefix`, in log order. An empty
+    /// prefix matches every entry.
+    ///
+    /// No index is maintained, this is a linear scan over the whole log.
+    pub fn prefix_search(&self, prefix: &str) -> Vec<String> {
+        self.iter()
+            .filter(|value| value.starts_with(prefix))
+            .collect()
+    }
+
+    /// Encode the edits that turn `base` into `self` as a compact binary
+    /// delta, to be applied to `base` with [`apply_delta`].
+    ///
+    /// The delta is a sequence of records, each a tag byte followed by a
+    /// little-endian `u64`: keep or delete that many entries, or insert a
+    /// value of that many UTF-8 bytes which follow. The edits come from a
+    /// longest common subsequence, which costs O(n · m) time and memory.
+    ///
+    /// [`apply_delta`]: TransactionLog::apply_delta
+    pub fn delta_from(&self, base: &TransactionLog) -> Vec<u8> {
+        let old: Vec<String> = base.iter().collect();
+        let new: Vec<String> = self.iter().collect();
+        // lcs[i][j] is the length of the longest common subsequence of
+        // `old[i..]` and `new[j..]`.
+        let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i][j] = if old[i] == new[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let mut ops = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                push_run(&mut ops, DeltaOp::Keep(1));
+                i += 1;
+                j += 1;
+            } else if j == new.len() || (i < old.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+                push_run(&mut ops, DeltaOp::Delete(1));
+                i += 1;
+            } else {
+                ops.push(DeltaOp::Insert(new[j].clone()));
+                j += 1;
+            }
+        }
+
+        let mut delta = Vec::new();
+        for op in ops {
+            op.encode(&mut delta);
+        }
+        delta
+    }
+
+    /// Apply a delta produced by [`delta_from`], turning the base log it was
+    /// computed against into the log it was computed from. The log is left
+    /// unchanged if the delta is malformed or doesn't fit it.
+    ///
+    /// [`delta_from`]: TransactionLog::delta_from
+    pub fn apply_delta(&mut self, delta: &[u8]) -> Result<(), DeltaError> {
+        let ops = DeltaOp::decode_all(delta)?;
+        // Every existing entry has to be kept or deleted exactly once.
+        let mut covered = 0usize;
+        for op in &ops {
+            if let DeltaOp::Keep(n) | DeltaOp::Delete(n) = op {
+                covered = covered.checked_add(*n).ok_or(DeltaError::BaseMismatch)?;
+            }
+        }
+        if covered != self.length {
+            return Err(DeltaError::BaseMismatch);
+        }
+
+        self.deferring(|log| {
+            let mut current = log.head.clone();
+            for op in ops {
+                match op {
+                    DeltaOp::Keep(n) => {
+                        for _ in 0..n {
+                            let node = current.take().expect("Checked against length");
+                            current = node.borrow().next.clone();
+                        }
+                    }
+                    DeltaOp::Delete(n) => {
+                        for _ in 0..n {
+                            let node = current.take().expect("Checked against length");
+                            current = node.borrow().next.clone();
+                            log.unlink(node);
+                        }
+                    }
+                    DeltaOp::Insert(value) => log.insert_value_before(current.clone(), value),
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Return a hash for every entry that combines its value with the hash of
+    /// the entry before it, the first entry being combined with
+    /// [`HASH_CHAIN_SEED`]. Changing any entry changes its hash and every hash
+    /// after it.
+    ///
+    /// The chain is recomputed on every call using 64-bit FNV-1a, so it stays
+    /// the same across runs and Rust versions.
+    pub fn hash_chain(&self) -> Vec<u64> {
+        let mut previous = HASH_CHAIN_SEED;
+        self.iter()
+            .map(|value| {
+                previous = chain_hash(previous, &value);
+                previous
+            })
+            .collect()
+    }
+
+    /// Recompute the [`hash_chain`] and return the index of the first entry
+    /// whose hash doesn't match `expected`, or `None` if the whole chain
+    /// matches. If one chain is a prefix of the other, the first index past
+    /// the shorter one is the mismatch.
+    ///
+    /// [`hash_chain`]: TransactionLog::hash_chain
+    pub fn verify_hash_chain(&self, expected: &[u64]) -> Option<usize> {
+        let actual = self.hash_chain();
+        if let Some(index) = actual.iter().zip(expected).position(|(a, e)| a != e) {
+            return Some(index);
+        }
+        (actual.len() != expected.len()).then(|| actual.len().min(expected.len()))
+    }
+
+    /// Estimate how compressible the contents are as the ratio of total bytes
+    /// to the bytes of the distinct values. Repeated values push the ratio
+    /// up, a log of all-distinct values scores `1.0`, as does an empty log.
+    ///
+    /// This is a cheap heuristic for deciding whether compression is worth
+    /// it, not a measurement of any real compressor.
+    pub fn estimated_compression_ratio(&self) -> f64 {
+        let total: usize = self.iter().map(|value| value.len()).sum();
+        let distinct: usize = self.value_counts().keys().map(|value| value.len()).sum();
+        if distinct == 0 {
+            return 1.0;
+        }
+        total as f64 / distinct as f64
+    }
+
+    /// Append `message` prefixed with its severity, like `"[WARN] message"`.
+    pub fn append_log(&mut self, level: Level, message: &str) {
+        self.append(format!("[{}] {}", level.as_str(), message));
+    }
+
+    /// Return the entries appended with [`append_log`] at `min` severity or
+    /// above. Entries without a recognised level prefix are skipped.
+    ///
+    /// [`append_log`]: TransactionLog::append_log
+    pub fn filter_level(&self, min: Level) -> Vec<String> {
+        self.iter()
+            .filter(|value| Level::of_entry(value).is_some_and(|level| level >= min))
+            .collect()
+    }
+
+    /// Run a script of commands against the `TransactionLog`, one per line:
+    /// `append <value>`, `push_front <value>`, `pop` and `clear`. Blank lines
+    /// are skipped. The whole script is parsed before anything runs, and if
+    /// any command fails every change made by the script is rolled back.
+    pub fn run_script(&mut self, script: &str) -> Result<(), ScriptError> {
+        let ops = script
+            .lines()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(i, text)| match LogOp::parse(text) {
+                Some(op) => Ok((i + 1, op)),
+                None => Err(ScriptError::Malformed {
+                    line: i + 1,
+                    text: text.to_string(),
+                }),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.deferring(|log| log.run_ops(ops))
+    }
+
+    /// Run parsed script commands, see [`run_script`].
+    ///
+    /// [`run_script`]: TransactionLog::run_script
+    fn run_ops(&mut self, ops: Vec<(usize, LogOp)>) -> Result<(), ScriptError> {
+        let redo = self.redo.clone();
+        let mut undo = Vec::new();
+        for (line, op) in ops {
+            let done = match op {
+                LogOp::Append(value) => {
+                    self.append(value);
+                    Ok(Undo::PopBack)
+                }
+                LogOp::AppendFront(value) => {
+                    self.append_front(value);
+                    Ok(Undo::Pop)
+                }
+                LogOp::Pop => self
+                    .take_front()
+                    .map(Undo::PushFront)
+                    .ok_or(ScriptError::EmptyLog { line }),
+                LogOp::Clear => {
+                    self.redo.clear();
+                    Ok(Undo::Restore(
+                        std::iter::from_fn(|| self.take_front()).collect(),
+                    ))
+                }
+            };
+            match done {
+                Ok(step) => undo.push(step),
+                Err(e) => {
+                    // Take the steps back most recent first, so each one sees
+                    // the log exactly as it left it.
+                    for step in undo.into_iter().rev() {
+                        match step {
+                            Undo::PopBack => {
+                                self.pop_back();
+                            }
+                            Undo::Pop => {
+                                self.pop();
+                            }
+                            Undo::PushFront(node) => self.push_front_node(node),
+                            Undo::Restore(nodes) => nodes
+                                .into_iter()
+                                .rev()
+                                .for_each(|n| self.push_front_node(n)),
+                        }
+                    }
+                    self.redo = redo;
+                    return Err(e);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T> Default for TransactionLog<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Clone> Clone for TransactionLog<T> {
+    /// Copy every value into a new, independent chain of nodes with the same
+    /// sequence ids, generations and capacity. Any `tee` writer or spill
+    /// file stays with the original.
+    fn clone(&self) -> Self {
+        let mut log = TransactionLog::new();
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let node = node.borrow();
+            log.append(node.value.clone());
+            if let Some(copy) = &log.tail {
+                let mut copy = copy.borrow_mut();
+                copy.id = node.id;
+                copy.generation = node.generation;
+            }
+            current = node.next.clone();
+        }
+        log.next_id = self.next_id;
+        log.capacity = self.capacity;
+        log
+    }
+}
+
+impl<T: PartialEq> PartialEq for TransactionLog<T> {
+    /// Two logs are equal when they hold equal values in the same order.
+    fn eq(&self, other: &Self) -> bool {
+        if self.length != other.length {
+            return false;
+        }
+        let mut ours = self.head.clone();
+        let mut theirs = other.head.clone();
+        loop {
+            match (ours, theirs) {
+                (Some(a), Some(b)) => {
+                    if a.borrow().value != b.borrow().value {
+                        return false;
+                    }
+                    ours = a.borrow().next.clone();
+                    theirs = b.borrow().next.clone();
+                }
+                (None, None) => return true,
+                _ => return false,
+            }
+        }
+    }
+}
+
+impl<T: Eq> Eq for TransactionLog<T> {}
+
+impl<T: fmt::Display> fmt::Display for TransactionLog<T> {
+    /// Write the values from head to tail as `[a, b, c]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[")?;
+        let mut separator = "";
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            let node = node.borrow();
+            write!(f, "{}{}", separator, node.value)?;
+            separator = ", ";
+            current = node.next.clone();
+        }
+        write!(f, "]")
+    }
+}
+
+impl<T> FromIterator<T> for TransactionLog<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut log = TransactionLog::new();
+        log.extend(iter);
+        log
+    }
+}
+
+impl<T> Extend<T> for TransactionLog<T> {
+    /// Append every value of `iter` in order.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for value in iter {
+            self.append(value);
+        }
+    }
+}
+
+/// Serializes as a sequence of the values from head to tail.
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize> serde::Serialize for TransactionLog<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(Some(self.length))?;
+        let mut current = self.head.clone();
+        while let Some(node) = current {
+            seq.serialize_element(&node.borrow().value)?;
+            current = node.borrow().next.clone();
+        }
+        seq.end()
+    }
+}
+
+/// Deserializes from a sequence of values, appending them in order.
+#[cfg(feature = "serde")]
+impl<'de, T: serde::Deserialize<'de>> serde::Deserialize<'de> for TransactionLog<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<T>::deserialize(deserializer).map(TransactionLog::from_iter)
+    }
+}
+
+impl<T> Drop for TransactionLog<T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// A [`TransactionLog`] that can only grow. It exposes appends and reads but
+/// deliberately none of the operations that drop entries, so code holding one
+/// can't lose data by accident.
+///
+/// ```compile_fail
+/// let mut log = transaction_log::AppendOnlyLog::new();
+/// log.append("Log Item 1".to_string());
+/// log.pop();
+/// ```
+#[derive(Debug)]
+pub struct AppendOnlyLog<T = String> {
+    log: TransactionLog<T>,
+}
+
+impl<T> AppendOnlyLog<T> {
+    pub fn new() -> AppendOnlyLog<T> {
+        AppendOnlyLog {
+            log: TransactionLog::new(),
+        }
+    }
+
+    /// Append a new value at the end of the log.
+    pub fn append(&mut self, value: T) {
+        self.log.append(value);
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log.length == 0
+    }
+
+    pub fn iter(&self) -> ListIterator<T> {
+        self.log.iter()
+    }
+
+    pub fn back_iter(&self) -> ListIterator<T> {
+        self.log.back_iter()
+    }
+
+    /// Borrow the wrapped log for any other read-only operation.
+    pub fn as_log(&self) -> &TransactionLog<T> {
+        &self.log
+    }
+}
+
+impl<T> Default for AppendOnlyLog<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> From<TransactionLog<T>> for AppendOnlyLog<T> {
+    fn from(log: TransactionLog<T>) -> Self {
+        AppendOnlyLog { log }
+    }
+}
+
+/// Writer registered through [`TransactionLog::tee`] along with the latched
+/// error of its last failed write.
+struct Tee<T> {
+    writer: Box<dyn Write>,
+    // Formats a single value onto `writer`, captured where `T: Display` is
+    // known so that `append` itself needs no bounds.
+    write_value: fn(&mut dyn Write, &T) -> io::Result<()>,
+    last_error: Option<io::Error>,
+}
+
+impl<T> Tee<T> {
+    fn write_line(&mut self, value: &T) {
+        if let Err(e) = (self.write_value)(&mut *self.writer, value) {
+            self.last_error = Some(e);
+        }
+    }
+}
+
+impl<T> fmt::Debug for Tee<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Tee")
+            .field("last_error", &self.last_error)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether `value` borrows as something equal to `other`.
+fn borrows_eq<T: std::borrow::Borrow<Q>, Q: PartialEq + ?Sized>(value: &T, other: &Q) -> bool {
+    value.borrow() == other
+}
+
+/// Backing file entries are moved to by [`TransactionLog::spill_after`].
+/// Every value is stored as its byte length, a little-endian `u64`, followed
+/// by its bytes.
+struct Spill<T> {
+    file: File,
+    path: PathBuf,
+    // Number of in-memory entries above which the oldest are spilled.
+    threshold: usize,
+    // Number of values, and bytes they take up, written so far.
+    count: usize,
+    bytes: u64,
+    encode: fn(&mut Vec<u8>, &T),
+    last_error: Option<io::Error>,
+}
+
+impl<T> Spill<T> {
+    /// Write `value` to the end of the file, returning whether it made it.
+    /// A failed write is cut off again so it can't corrupt later records.
+    fn write(&mut self, value: &T) -> bool {
+        let mut record = Vec::new();
+        (self.encode)(&mut record, value);
+        match self.file.write_all(&record) {
+            Ok(()) => {
+                self.count += 1;
+                self.bytes += record.len() as u64;
+                true
+            }
+            Err(e) => {
+                let _ = self.file.set_len(self.bytes);
+                let _ = self.file.seek(SeekFrom::Start(self.bytes));
+                self.last_error = Some(e);
+                false
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Spill<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Spill")
+            .field("path", &self.path)
+            .field("threshold", &self.threshold)
+            .field("count", &self.count)
+            .field("last_error", &self.last_error)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads back the first `remaining` values of a spill file.
+struct SpilledValues {
+    reader: BufReader<File>,
+    remaining: usize,
+}
+
+impl SpilledValues {
+    fn read_value(&mut self) -> io::Result<String> {
+        let mut len = [0; 8];
+        self.reader.read_exact(&mut len)?;
+        let len = usize::try_from(u64::from_le_bytes(len))
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let mut bytes = vec![0; len];
+        self.reader.read_exact(&mut bytes)?;
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+}
+
+impl Iterator for SpilledValues {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let value = self.read_value();
+        if value.is_err() {
+            // The rest of the file can't be trusted to line up any more.
+            self.remaining = 0;
+        }
+        Some(value)
+    }
+}
+
+/// Character-level Levenshtein distance between `a` and `b`.
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    // Distances from the previous prefix of `a` to every prefix of `b`.
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+const DELTA_KEEP: u8 = 0;
+const DELTA_DELETE: u8 = 1;
+const DELTA_INSERT: u8 = 2;
+
+/// A single record of a binary delta, see [`TransactionLog::delta_from`].
+#[derive(Debug, PartialEq, Eq)]
+enum DeltaOp {
+    Keep(usize),
+    Delete(usize),
+    Insert(String),
+}
+
+impl DeltaOp {
+    fn encode(&self, out: &mut Vec<u8>) {
+        let (tag, n, bytes) = match self {
+            DeltaOp::Keep(n) => (DELTA_KEEP, *n, &[][..]),
+            DeltaOp::Delete(n) => (DELTA_DELETE, *n, &[][..]),
+            DeltaOp::Insert(value) => (DELTA_INSERT, value.len(), value.as_bytes()),
+        };
+        out.push(tag);
+        out.extend_from_slice(&(n as u64).to_le_bytes());
+        out.extend_from_slice(bytes);
+    }
+
+    fn decode_all(mut delta: &[u8]) -> Result<Vec<DeltaOp>, DeltaError> {
+        let mut ops = Vec::new();
+        while let Some((&tag, rest)) = delta.split_first() {
+            let (n, rest) = rest.split_first_chunk::<8>().ok_or(DeltaError::Truncated)?;
+            let n =
+                usize::try_from(u64::from_le_bytes(*n)).map_err(|_| DeltaError::BaseMismatch)?;
+            delta = rest;
+            ops.push(match tag {
+                DELTA_KEEP => DeltaOp::Keep(n),
+                DELTA_DELETE => DeltaOp::Delete(n),
+                DELTA_INSERT => {
+                    if delta.len() < n {
+                        return Err(DeltaError::Truncated);
+                    }
+                    let (bytes, rest) = delta.split_at(n);
+                    delta = rest;
+                    let value =
+                        String::from_utf8(bytes.to_vec()).map_err(|_| DeltaError::InvalidUtf8)?;
+                    DeltaOp::Insert(value)
+                }
+                tag => return Err(DeltaError::UnknownTag(tag)),
+            });
+        }
+        Ok(ops)
+    }
+}
+
+/// Push `op` onto `ops`, merging it into the last op if both are runs of the
+/// same kind.
+fn push_run(ops: &mut Vec<DeltaOp>, op: DeltaOp) {
+    match (ops.last_mut(), op) {
+        (Some(DeltaOp::Keep(n)), DeltaOp::Keep(m))
+        | (Some(DeltaOp::Delete(n)), DeltaOp::Delete(m)) => *n += m,
+        (_, op) => ops.push(op),
+    }
+}
+
+/// Checksum maintained by a `TransactionLog` once [`TransactionLog::checksum`]
+/// has been asked for.
+struct RunningChecksum<T> {
+    sum: u64,
+    // Hash of a single value, captured where `T: Hash` is known so that
+    // `append` and `pop` need no bounds.
+    hash: fn(&T) -> u64,
+}
+
+impl<T> Clone for RunningChecksum<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for RunningChecksum<T> {}
+
+impl<T> fmt::Debug for RunningChecksum<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RunningChecksum")
+            .field("sum", &self.sum)
+            .finish_non_exhaustive()
+    }
+}
+
+fn value_hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash the first entry of a [`TransactionLog::hash_chain`] is chained to.
+pub const HASH_CHAIN_SEED: u64 = 0;
+
+/// 64-bit FNV-1a over the previous hash followed by the bytes of `value`.
+fn chain_hash(previous: u64, value: &str) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+    let mut hash = FNV_OFFSET_BASIS;
+    for byte in previous.to_le_bytes().iter().chain(value.as_bytes()) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+    hash
+}
+
+/// Error returned when the entries of a `TransactionLog` can't be treated as
+/// integers. Entries are counted from the head.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The entry at `index` can't be parsed as an integer.
+    NotAnInteger { index: usize, value: String },
+    /// Arithmetic on the entry at `index` doesn't fit in an `i64`.
+    Overflow { index: usize, value: String },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NotAnInteger { index, value } => {
+                write!(f, "entry {} ({:?}) is not an integer", index, value)
+            }
+            ParseError::Overflow { index, value } => {
+                write!(f, "entry {} ({:?}) overflows an i64", index, value)
+            }
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+/// Error returned when a [`TransactionLog::run_script`] script fails. Lines
+/// are counted from 1.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    /// The line isn't a known command with the right arguments.
+    Malformed { line: usize, text: String },
+    /// The line pops from an empty log.
+    EmptyLog { line: usize },
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Malformed { line, text } => {
+                write!(f, "line {}: malformed command {:?}", line, text)
+            }
+            ScriptError::EmptyLog { line } => write!(f, "line {}: pop from an empty log", line),
+        }
+    }
+}
+
+impl Error for ScriptError {}
+
+/// Error returned when rolling back to a [`Checkpoint`] after entries up to
+/// it have been removed from the `TransactionLog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointError;
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "checkpoint is no longer part of the log")
+    }
+}
+
+impl Error for CheckpointError {}
+
+/// Error returned when appending to a `TransactionLog` would exceed its cap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CapacityError<T = String> {
+    /// The value that was rejected.
+    pub value: T,
+    /// The cap that would have been exceeded.
+    pub max: usize,
+}
+
+impl<T> fmt::Display for CapacityError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "log is full ({} entries)", self.max)
+    }
+}
+
+impl<T: fmt::Debug> Error for CapacityError<T> {}
+
+/// Error returned when a binary delta can't be applied to a `TransactionLog`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeltaError {
+    /// The delta ends in the middle of a record.
+    Truncated,
+    /// A record starts with an unknown tag byte.
+    UnknownTag(u8),
+    /// An inserted value isn't valid UTF-8.
+    InvalidUtf8,
+    /// The delta doesn't keep or delete exactly the entries of the log, so
+    /// it was computed against a different base.
+    BaseMismatch,
+}
+
+impl fmt::Display for DeltaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeltaError::Truncated => write!(f, "delta is truncated"),
+            DeltaError::UnknownTag(tag) => write!(f, "unknown delta record tag {}", tag),
+            DeltaError::InvalidUtf8 => write!(f, "inserted value is not valid UTF-8"),
+            DeltaError::BaseMismatch => write!(f, "delta does not match the base log"),
+        }
+    }
+}
+
+impl Error for DeltaError {}
+
+/// Severity of an entry appended with [`TransactionLog::append_log`], ordered
+/// from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl Level {
+    const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warn, Level::Error];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warn => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+
+    /// Parse the level out of an entry's `"[LEVEL] "` prefix.
+    fn of_entry(entry: &str) -> Option<Level> {
+        let (tag, _) = entry.strip_prefix('[')?.split_once("] ")?;
+        Level::ALL.into_iter().find(|level| level.as_str() == tag)
+    }
+}
+
+/// A cursor over a `TransactionLog` that can insert and remove entries
+/// anywhere, returned by [`TransactionLog::cursor_mut`].
+///
+/// The cursor is either on an entry or on a "ghost" position past the tail
+/// and before the head, which it reaches by moving off either end. Moving on
+/// from the ghost wraps around to the other end.
+pub struct CursorMut<'a, T = String> {
+    log: &'a mut TransactionLog<T>,
+    // Weak so that a cursor that's still around doesn't keep a node alive
+    // after it's popped.
+    current: WeakLink<T>,
+}
+
+impl<T> CursorMut<'_, T> {
+    fn current_node(&self) -> Link<T> {
+        self.current.as_ref().and_then(Weak::upgrade)
+    }
+
+    fn set_current(&mut self, node: Link<T>) {
+        self.current = node.as_ref().map(Rc::downgrade);
+    }
+
+    /// Move to the next entry, or to the head when on the ghost.
+    pub fn move_next(&mut self) {
+        let next = match self.current_node() {
+            Some(node) => node.borrow().next.clone(),
+            None => self.log.head.clone(),
+        };
+        self.set_current(next);
+    }
+
+    /// Move to the previous entry, or to the tail when on the ghost.
+    pub fn move_prev(&mut self) {
+        let prev = match self.current_node() {
+            Some(node) => node.borrow().prev.as_ref().and_then(Weak::upgrade),
+            None => self.log.tail.clone(),
+        };
+        self.set_current(prev);
+    }
+
+    /// Insert `value` after the current entry, or at the head when on the
+    /// ghost. The cursor doesn't move.
+    pub fn insert_after(&mut self, value: T) {
+        let after = match self.current_node() {
+            Some(node) => node.borrow().next.clone(),
+            None => self.log.head.clone(),
+        };
+        self.log.insert_value_before(after, value);
+    }
+
+    /// Insert `value` before the current entry, or at the tail when on the
+    /// ghost. The cursor doesn't move.
+    pub fn insert_before(&mut self, value: T) {
+        let after = self.current_node();
+        self.log.insert_value_before(after, value);
+    }
+
+    /// Remove the current entry and return its value, moving the cursor on to
+    /// the next entry. Returns `None` on the ghost.
+    pub fn remove_current(&mut self) -> Option<T> {
+        let node = self.current_node()?;
+        let next = node.borrow().next.clone();
+        self.set_current(next);
+        Some(self.log.unlink(node))
+    }
+}
+
+impl<T: Clone> CursorMut<'_, T> {
+    /// Return a copy of the current value, or `None` on the ghost.
+    pub fn current(&self) -> Option<T> {
+        self.current_node().map(|node| node.borrow().value.clone())
+    }
+}
+
+/// Snapshot of a `TransactionLog`'s values with O(1) access by index,
+/// returned by [`TransactionLog::as_indexed`]. Later changes to the log
+/// aren't reflected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedView<T = String> {
+    values: Vec<T>,
+}
+
+impl<T> IndexedView<T> {
+    /// Return the value at `index`, counting from the head.
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.values.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+/// A marked position in a `TransactionLog`, see
+/// [`TransactionLog::checkpoint`].
+#[derive(Debug)]
+pub struct Checkpoint<T = String> {
+    // The ends when the checkpoint was taken, `None` for an empty log.
+    head: WeakLink<T>,
+    tail: WeakLink<T>,
+    length: usize,
+}
+
+/// A recorded mutation of a `TransactionLog`, see
+/// [`TransactionLog::replay_ops`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogOp<T = String> {
+    /// Append the value at the back.
+    Append(T),
+    /// Pop a value from the front.
+    Pop,
+    /// Insert the value at the front.
+    AppendFront(T),
+    /// Remove every value.
+    Clear,
+}
+
+impl LogOp {
+    /// Parse one line of a [`TransactionLog::run_script`] script.
+    fn parse(line: &str) -> Option<LogOp> {
+        let line = line.trim();
+        let (command, argument) = match line.split_once(' ') {
+            Some((command, argument)) => (command, Some(argument.to_string())),
+            None => (line, None),
+        };
+        match (command, argument) {
+            ("append", Some(value)) => Some(LogOp::Append(value)),
+            ("push_front", Some(value)) => Some(LogOp::AppendFront(value)),
+            ("pop", None) => Some(LogOp::Pop),
+            ("clear", None) => Some(LogOp::Clear),
+            _ => None,
+        }
+    }
+}
+
+/// How to take back one operation applied by [`TransactionLog::run_script`].
+enum Undo {
+    PopBack,
+    Pop,
+    // Removed nodes are kept whole so they go back with their ids and
+    // generations.
+    PushFront(Rc<RefCell<Node<String>>>),
+    Restore(Vec<Rc<RefCell<Node<String>>>>),
+}
+
+pub struct ListIterator<T> {
+    // Saves a reference to the current node.
+    current_link: Link<T>,
+}
+
+impl<T> ListIterator<T> {
+    fn new(start_at: Link<T>) -> ListIterator<T> {
+        ListIterator {
+            current_link: start_at,
+        }
+    }
+}
+
+impl<T: Clone> Iterator for ListIterator<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current_link = &self.current_link;
+        let mut result = None;
+        self.current_link = match current_link {
+            // There is a current node.
+            Some(current) => {
+                let current_node = current.borrow();
+                // Grab the current node value and return it as the result.
+                result = Some(current_node.value.clone());
+                // Update the `ListIterator.current_link` field with the next
+                // node in the list.
+                current_node.next.clone()
+            }
+            // There is no current node. We're done iterating.
+            None => None,
+        };
+
+        result
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for ListIterator<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let current_link = &self.current_link;
+        let mut result = None;
+        // There is a current node.
+        self.current_link = match current_link {
+            Some(current) => {
+                let current_node = current.borrow();
+                result = Some(current_node.value.clone());
+                // Update the `ListIterator.current_link` field with the
+                // previous node in the list.
+                current_node.prev.as_ref().and_then(Weak::upgrade)
+            }
+            // There is no current node. We're done iterating.
+            None => None,
+        };
+
+        result
+    }
+}
+
+/// Iterator returned by [`TransactionLog::indexed_iter`] and
+/// [`TransactionLog::indexed_back_iter`], yielding `(index, value)` pairs
+/// where `index` counts from the head.
+///
+/// Like [`ListIterator`] this is a single cursor: `next` yields the current
+/// entry and steps towards the tail, `next_back` yields it and steps towards
+/// the head. Directions can be mixed freely and the index always matches
+/// the entry it's yielded with.
+pub struct IndexedListIterator<T> {
+    values: ListIterator<T>,
+    // Index of the entry the cursor is on.
+    index: usize,
+}
+
+impl<T: Clone> Iterator for IndexedListIterator<T> {
+    type Item = (usize, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let value = self.values.next()?;
+        let index = self.index;
+        self.index += 1;
+        Some((index, value))
+    }
+}
+
+impl<T: Clone> DoubleEndedIterator for IndexedListIterator<T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let value = self.values.next_back()?;
+        let index = self.index;
+        // Stepping back from the head leaves the cursor past the end, where
+        // the index no longer matters.
+        self.index = self.index.saturating_sub(1);
+        Some((index, value))
+    }
+}
+
+/// Consuming iterator returned by [`TransactionLog::into_iter_rev`].
+pub struct IntoIterRev<T> {
+    log: TransactionLog<T>,
+}
+
+impl<T> Iterator for IntoIterRev<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.log.pop_back()
+    }
+}
+
+/// Consuming iterator over a [`TransactionLog`], yielding owned values from
+/// head to tail.
+pub struct IntoIter<T> {
+    log: TransactionLog<T>,
+}
+
+impl<T> Iterator for IntoIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.log.pop()
+    }
+}
+
+impl<T> IntoIterator for TransactionLog<T> {
+    type Item = T;
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> IntoIter<T> {
+        IntoIter { log: self }
+    }
+}
+
+/// Iterator returned by [`TransactionLog::records`].
+pub struct Records<T: Clone, F> {
+    values: Peekable<ListIterator<T>>,
+    is_continuation: F,
+}
+
+impl<T: Clone, F: FnMut(&T) -> bool> Iterator for Records<T, F> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut record = vec![self.values.next()?];
+        while let Some(value) = self.values.next_if(|value| (self.is_continuation)(value)) {
+            record.push(value);
+        }
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log_of(values: &[&str]) -> TransactionLog {
+        let mut tl = TransactionLog::new();
+        for v in values {
+            tl.append(v.to_string());
+        }
+        tl
+    }
+
+    /// Collect `tl` front to back, asserting walking it back to front agrees.
+    fn values_of(tl: &TransactionLog) -> Vec<String> {
+        let forward: Vec<String> = tl.iter().collect();
+        let mut backward: Vec<String> = tl.back_iter().rev().collect();
+        backward.reverse();
+        assert_eq!(forward, backward);
+        assert_eq!(forward.len(), tl.length);
+        forward
+    }
+
+    #[test]
+    fn new_transaction_log_can_be_created() {
+        let tl: TransactionLog = TransactionLog::new();
+
+        assert_eq!(tl.length, 0);
+    }
+
+    #[test]
+    fn items_can_be_appended_and_popped_from_transaction_log() {
+        let mut tl = TransactionLog::new();
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+        tl.append("Log Item 3".to_string());
+
+        assert_eq!(tl.length, 3);
+        assert_eq!(tl.pop(), Some("Log Item 1".to_string()));
+        assert_eq!(tl.pop(), Some("Log Item 2".to_string()));
+        assert_eq!(tl.pop(), Some("Log Item 3".to_string()));
+        assert_eq!(tl.pop(), None);
+    }
+
+    #[test]
+    fn transaction_log_can_be_forward_iterated() {
+        let mut tl = TransactionLog::new();
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+        tl.append("Log Item 3".to_string());
+
+        assert_eq!(tl.iter().count(), 3);
+        for t in tl.iter().zip([
+            "Log Item 1".to_string(),
+            "Log Item 2".to_string(),
+            "Log Item 3".to_string(),
+        ]) {
+            assert_eq!(t.0, t.1)
+        }
+    }
+
+    #[test]
+    fn transaction_log_can_be_backward_iterated() {
+        let mut tl = TransactionLog::new();
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+        tl.append("Log Item 3".to_string());
+
+        assert_eq!(tl.back_iter().rev().count(), 3);
+        for t in tl.back_iter().rev().zip([
+            "Log Item 3".to_string(),
+            "Log Item 2".to_string(),
+            "Log Item 1".to_string(),
+        ]) {
+            assert_eq!(t.0, t.1)
+        }
+    }
+
+    #[test]
+    fn transaction_log_can_be_rotated_to_a_value() {
+        let mut tl = TransactionLog::new();
+        for v in ["a", "b", "c", "d"] {
+            tl.append(v.to_string());
+        }
+
+        assert!(tl.rotate_to("c"));
+        assert_eq!(tl.length, 4);
+        assert_eq!(tl.iter().collect::<Vec<_>>(), ["c", "d", "a", "b"]);
+        assert_eq!(
+            tl.back_iter().rev().collect::<Vec<_>>(),
+            ["b", "a", "d", "c"]
+        );
+    }
+
+    #[test]
+    fn rotating_to_a_missing_value_leaves_transaction_log_unchanged() {
+        let mut tl = TransactionLog::new();
+        for v in ["a", "b", "c"] {
+            tl.append(v.to_string());
+        }
+
+        assert!(tl.rotate_to("a"));
+        assert!(!tl.rotate_to("z"));
+        assert_eq!(tl.iter().collect::<Vec<_>>(), ["a", "b", "c"]);
+        assert!(!TransactionLog::<String>::new().rotate_to("a"));
+    }
+
+    #[test]
+    fn transaction_log_keeps_at_most_n_distinct_values() {
+        let mut tl = TransactionLog::new();
+        for v in ["a", "a", "b", "a", "c", "b", "d"] {
+            tl.append(v.to_string());
+        }
+
+        tl.keep_distinct(4);
+        assert_eq!(tl.length, 7);
+
+        // Evicting the leading "a"s isn't enough, "a" still occurs later.
+        tl.keep_distinct(3);
+        assert_eq!(tl.iter().collect::<Vec<_>>(), ["c", "b", "d"]);
+
+        tl.keep_distinct(0);
+        assert_eq!(tl.length, 0);
+        assert_eq!(tl.pop(), None);
+    }
+
+    #[test]
+    fn items_can_be_popped_from_the_back_of_transaction_log() {
+        let mut tl = TransactionLog::new();
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+
+        assert_eq!(tl.pop_back(), Some("Log Item 2".to_string()));
+        assert_eq!(tl.pop_back(), Some("Log Item 1".to_string()));
+        assert_eq!(tl.pop_back(), None);
+        assert_eq!(tl.length, 0);
+    }
+
+    #[test]
+    fn pop_and_pop_back_can_be_interleaved_with_appends() {
+        let mut tl = TransactionLog::new();
+        tl.append("Log Item 1".to_string());
+        assert_eq!(tl.pop_back(), Some("Log Item 1".to_string()));
+        assert_eq!(values_of(&tl), Vec::<String>::new());
+        assert_eq!(tl.pop(), None);
+
+        tl.append("Log Item 2".to_string());
+        tl.append("Log Item 3".to_string());
+        tl.append("Log Item 4".to_string());
+        assert_eq!(tl.pop(), Some("Log Item 2".to_string()));
+        assert_eq!(tl.pop_back(), Some("Log Item 4".to_string()));
+        tl.append("Log Item 5".to_string());
+        assert_eq!(values_of(&tl), ["Log Item 3", "Log Item 5"]);
+
+        assert_eq!(tl.pop_back(), Some("Log Item 5".to_string()));
+        assert_eq!(tl.pop(), Some("Log Item 3".to_string()));
+        assert_eq!(tl.pop_back(), None);
+        tl.append("Log Item 6".to_string());
+        assert_eq!(values_of(&tl), ["Log Item 6"]);
+    }
+
+    #[test]
+    fn items_can_be_appended_to_the_front_of_transaction_log() {
+        let mut tl = TransactionLog::new();
+        tl.append_front("Log Item 1".to_string());
+        tl.append_front("Log Item 2".to_string());
+        tl.append_front("Log Item 3".to_string());
+        assert_eq!(values_of(&tl), ["Log Item 3", "Log Item 2", "Log Item 1"]);
+
+        assert_eq!(tl.pop_back(), Some("Log Item 1".to_string()));
+        assert_eq!(tl.pop(), Some("Log Item 3".to_string()));
+        assert_eq!(tl.pop(), Some("Log Item 2".to_string()));
+        assert_eq!(tl.pop(), None);
+    }
+
+    #[test]
+    fn front_and_back_appends_can_be_mixed() {
+        let mut tl = TransactionLog::new();
+        tl.append("b".to_string());
+        tl.append_front("a".to_string());
+        tl.append("c".to_string());
+        tl.append_front("begin".to_string());
+        assert_eq!(values_of(&tl), ["begin", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_consumed_in_order() {
+        let tl = log_of(&["Log Item 1", "Log Item 2", "Log Item 3"]);
+        let mut seen = Vec::new();
+        for v in tl {
+            seen.push(v);
+        }
+        assert_eq!(seen, ["Log Item 1", "Log Item 2", "Log Item 3"]);
+
+        // Every value the log owned is handed out, nothing stays behind.
+        let marker = Rc::new(());
+        let mut tl = TransactionLog::new();
+        tl.append(marker.clone());
+        tl.append(marker.clone());
+        let mut values = tl.into_iter();
+        assert!(values.next().is_some());
+        assert_eq!(Rc::strong_count(&marker), 2);
+        assert!(values.next().is_some());
+        assert!(values.next().is_none());
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn transaction_log_can_be_consumed_in_reverse() {
+        let mut tl = TransactionLog::new();
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+        tl.append("Log Item 3".to_string());
+
+        assert_eq!(
+            tl.into_iter_rev().collect::<Vec<_>>(),
+            ["Log Item 3", "Log Item 2", "Log Item 1"]
+        );
+    }
+
+    #[test]
+    fn transaction_log_deltas_invert_running_sums() {
+        let mut tl = TransactionLog::new();
+        for v in ["1", "3", "6"] {
+            tl.append(v.to_string());
+        }
+
+        let deltas = tl.deltas().unwrap();
+        assert_eq!(deltas.iter().collect::<Vec<_>>(), ["1", "2", "3"]);
+        assert_eq!(TransactionLog::new().deltas().unwrap().length, 0);
+    }
+
+    #[test]
+    fn transaction_log_deltas_reject_non_numeric_entries() {
+        let mut tl = TransactionLog::new();
+        tl.append("1".to_string());
+        tl.append("two".to_string());
+
+        assert_eq!(
+            tl.deltas().unwrap_err(),
+            ParseError::NotAnInteger {
+                index: 1,
+                value: "two".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn transaction_log_deltas_reject_overflowing_differences() {
+        let mut tl = TransactionLog::new();
+        tl.append(i64::MIN.to_string());
+        tl.append("1".to_string());
+
+        assert_eq!(
+            tl.deltas().unwrap_err(),
+            ParseError::Overflow {
+                index: 1,
+                value: "1".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn transaction_log_mode_is_the_most_frequent_value() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.mode(), None);
+
+        for v in ["a", "b", "a", "c", "a", "b"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.mode(), Some("a".to_string()));
+
+        // "b" and "c" both occur twice, but "c" got there first.
+        let mut tl = TransactionLog::new();
+        for v in ["b", "c", "c", "b"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.mode(), Some("c".to_string()));
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn transaction_log_entries_can_be_validated_against_a_regex() {
+        let mut tl = TransactionLog::new();
+        tl.append("123".to_string());
+        tl.append("42".to_string());
+
+        assert!(tl.all_match(r"^\d+$").unwrap());
+        assert!(tl.all_match(r"\d+").unwrap());
+        // A partial match isn't enough.
+        assert!(!tl.all_match(r"\d").unwrap());
+        tl.append("12a".to_string());
+        assert!(!tl.all_match(r"^\d+$").unwrap());
+        assert!(tl.all_match("(").is_err());
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn capture_groups_can_be_extracted_from_transaction_log() {
+        let mut tl = TransactionLog::new();
+        tl.append("id=42 name=foo".to_string());
+        tl.append("no id here".to_string());
+        tl.append("id=7 name=bar".to_string());
+
+        let ids = tl.extract(r"id=(\d+)", 1).unwrap();
+        assert_eq!(ids.iter().collect::<Vec<_>>(), ["42", "7"]);
+        assert!(tl.extract("(", 1).is_err());
+    }
+
+    #[test]
+    fn matching_entries_can_be_extracted_from_transaction_log() {
+        let mut tl = TransactionLog::new();
+        for v in ["ERROR 1", "INFO 1", "ERROR 2", "INFO 2", "ERROR 3"] {
+            tl.append(v.to_string());
+        }
+
+        let errors = tl.extract_matching(|v| v.starts_with("ERROR"));
+        assert_eq!(errors.length, 3);
+        assert_eq!(
+            errors.iter().collect::<Vec<_>>(),
+            ["ERROR 1", "ERROR 2", "ERROR 3"]
+        );
+        assert_eq!(tl.length, 2);
+        assert_eq!(tl.iter().collect::<Vec<_>>(), ["INFO 1", "INFO 2"]);
+        assert_eq!(
+            tl.back_iter().rev().collect::<Vec<_>>(),
+            ["INFO 2", "INFO 1"]
+        );
+    }
+
+    #[test]
+    fn transaction_log_entries_can_be_summed_by_weight() {
+        let mut tl: TransactionLog = TransactionLog::new();
+        assert_eq!(tl.weighted_sum(|v| v.len() as f64), 0.0);
+
+        for v in ["a", "bb", "cccc"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.weighted_sum(|v| v.len() as f64), 1.0 + 2.0 + 4.0);
+    }
+
+    #[test]
+    fn transaction_logs_can_be_compared_up_to_a_prefix() {
+        let mut tl = TransactionLog::new();
+        let mut other = TransactionLog::new();
+        for v in ["a", "b", "c"] {
+            tl.append(v.to_string());
+        }
+        for v in ["a", "b", "x", "y"] {
+            other.append(v.to_string());
+        }
+
+        assert!(tl.eq_prefix(&other, 0));
+        assert!(tl.eq_prefix(&other, 2));
+        assert!(!tl.eq_prefix(&other, 3));
+
+        // Past the end of the shorter log the lengths differ.
+        other.pop_back();
+        other.pop_back();
+        assert!(other.eq_prefix(&tl, 2));
+        assert!(!other.eq_prefix(&tl, 3));
+    }
+
+    #[test]
+    fn transaction_log_can_be_reduced_over_windows() {
+        let mut tl = TransactionLog::new();
+        for v in ["1", "3", "5", "7", "9"] {
+            tl.append(v.to_string());
+        }
+
+        let average = |w: &[String]| {
+            let sum: i64 = w.iter().map(|v| v.parse::<i64>().unwrap()).sum();
+            (sum / w.len() as i64).to_string()
+        };
+        // The trailing "9" doesn't fill a window and is dropped.
+        let reduced = tl.reduce_windows(2, 2, average);
+        assert_eq!(reduced.iter().collect::<Vec<_>>(), ["2", "6"]);
+
+        let reduced = tl.reduce_windows(2, 1, average);
+        assert_eq!(reduced.iter().collect::<Vec<_>>(), ["2", "4", "6", "8"]);
+        assert_eq!(tl.reduce_windows(6, 1, average).length, 0);
+    }
+
+    #[test]
+    fn key_value_pairs_round_trip_through_transaction_log() {
+        let pairs = vec![
+            ("HOME".to_string(), "/root".to_string()),
+            ("EQUATION".to_string(), "a=b".to_string()),
+        ];
+
+        let mut tl = TransactionLog::from_pairs(&pairs);
+        assert_eq!(
+            tl.iter().collect::<Vec<_>>(),
+            ["HOME=/root", "EQUATION=a=b"]
+        );
+
+        tl.append("malformed".to_string());
+        assert_eq!(tl.parse_pairs(), pairs);
+    }
+
+    #[test]
+    fn transaction_log_median_entry_is_the_middle_by_position() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.median_entry(), None);
+
+        for v in ["e", "d", "c", "b", "a"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.median_entry(), Some("c".to_string()));
+    }
+
+    #[test]
+    fn appending_past_the_cap_returns_an_error() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.append_capped("Log Item 1".to_string(), 2), Ok(()));
+        assert_eq!(tl.append_capped("Log Item 2".to_string(), 2), Ok(()));
+
+        assert_eq!(
+            tl.append_capped("Log Item 3".to_string(), 2),
+            Err(CapacityError {
+                value: "Log Item 3".to_string(),
+                max: 2
+            })
+        );
+        assert_eq!(tl.length, 2);
+        assert_eq!(tl.iter().collect::<Vec<_>>(), ["Log Item 1", "Log Item 2"]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_rebuilt_from_recorded_ops() {
+        let ops = [
+            LogOp::Append("a".to_string()),
+            LogOp::Append("b".to_string()),
+            LogOp::Pop,
+            LogOp::Append("c".to_string()),
+        ];
+
+        let tl = TransactionLog::replay_ops(&ops);
+        assert_eq!(tl.length, 2);
+        assert_eq!(tl.iter().collect::<Vec<_>>(), ["b", "c"]);
+        // Popping an empty log is a no-op.
+        assert_eq!(
+            TransactionLog::<String>::replay_ops(&[LogOp::Pop]).length,
+            0
+        );
+    }
+
+    #[test]
+    fn transaction_log_medoid_is_the_most_central_entry() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.medoid(), None);
+
+        for v in ["cart", "cat", "bat", "cast", "hat"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.medoid(), Some("cat".to_string()));
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+    }
+
+    #[test]
+    fn transaction_log_can_be_iterated_pairwise() {
+        let mut tl = TransactionLog::new();
+        tl.append("a".to_string());
+        assert_eq!(tl.pairwise().count(), 0);
+
+        tl.append("b".to_string());
+        tl.append("c".to_string());
+        assert_eq!(
+            tl.pairwise().collect::<Vec<_>>(),
+            [
+                ("a".to_string(), "b".to_string()),
+                ("b".to_string(), "c".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn blank_entries_can_be_removed_from_transaction_log() {
+        let mut tl = TransactionLog::new();
+        for v in [
+            "",
+            "Log Item 1",
+            "  ",
+            "Log Item 2",
+            "\t\n",
+            "Log Item 3",
+            "",
+        ] {
+            tl.append(v.to_string());
+        }
+
+        assert_eq!(tl.remove_blank(), 4);
+        assert_eq!(tl.length, 3);
+        assert_eq!(
+            tl.iter().collect::<Vec<_>>(),
+            ["Log Item 1", "Log Item 2", "Log Item 3"]
+        );
+        assert_eq!(tl.back_iter().rev().count(), 3);
+        assert_eq!(tl.remove_blank(), 0);
+    }
+
+    #[test]
+    fn transaction_log_finds_the_longest_increasing_run() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.longest_increasing_run(), Ok((0, 0)));
+
+        for v in ["1", "2", "3", "1", "5", "6", "7", "8"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.longest_increasing_run(), Ok((3, 5)));
+
+        // Equal neighbours break a strictly increasing run.
+        let mut tl = TransactionLog::new();
+        for v in ["4", "5", "5", "6"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.longest_increasing_run(), Ok((0, 2)));
+
+        tl.append("x".to_string());
+        assert!(tl.longest_increasing_run().is_err());
+    }
+
+    /// Writer that keeps its bytes reachable after being moved into a `tee`.
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn appended_values_are_mirrored_into_the_tee() {
+        let mut tl = TransactionLog::new();
+        tl.append("Before tee".to_string());
+
+        let buffer = Rc::new(RefCell::new(Vec::new()));
+        tl.tee(SharedBuffer(buffer.clone()));
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+
+        assert_eq!(*buffer.borrow(), b"Log Item 1\nLog Item 2\n");
+        assert!(tl.last_tee_error().is_none());
+        assert_eq!(tl.length, 3);
+    }
+
+    #[test]
+    fn tee_write_errors_are_latched_without_losing_the_append() {
+        let mut tl = TransactionLog::new();
+        tl.tee(FailingWriter);
+        tl.append("Log Item 1".to_string());
+
+        assert_eq!(tl.last_tee_error().unwrap().to_string(), "disk full");
+        assert_eq!(tl.pop(), Some("Log Item 1".to_string()));
+    }
+
+    #[test]
+    fn values_only_in_one_transaction_log_can_be_listed() {
+        let mut tl = TransactionLog::new();
+        let mut other = TransactionLog::new();
+        for v in ["a", "b", "a", "c"] {
+            tl.append(v.to_string());
+        }
+        other.append("b".to_string());
+
+        assert_eq!(tl.values_only_in(&other), ["a", "c"]);
+        assert!(other.values_only_in(&tl).is_empty());
+    }
+
+    #[test]
+    fn appended_entries_get_increasing_sequence_ids() {
+        let mut tl = TransactionLog::new();
+        for v in ["a", "b", "c"] {
+            tl.append(v.to_string());
+        }
+        tl.pop();
+
+        assert_eq!(tl.sequence_ids(), [2, 3]);
+    }
+
+    #[test]
+    fn sequence_ids_resume_after_reseeding() {
+        // Stands in for a log restored from disk with ids up to 5.
+        let mut tl = TransactionLog::new();
+        for v in ["a", "b", "c", "d", "e"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(tl.sequence_ids(), [1, 2, 3, 4, 5]);
+
+        let max_id = tl.sequence_ids().into_iter().max().unwrap();
+        tl.reseed_sequence(max_id + 1);
+        tl.append("f".to_string());
+        assert_eq!(tl.sequence_ids().last(), Some(&6));
+
+        tl.reseed_sequence(100);
+        tl.append("g".to_string());
+        assert_eq!(tl.sequence_ids().last(), Some(&100));
+    }
+
+    #[test]
+    fn first_entry_over_a_length_threshold_can_be_found() {
+        let mut tl = TransactionLog::new();
+        for v in ["short", "exactly10!", "much too long", "also too long"] {
+            tl.append(v.to_string());
+        }
+
+        assert_eq!(
+            tl.first_exceeding(10),
+            Some((2, "much too long".to_string()))
+        );
+        assert_eq!(tl.first_exceeding(13), None);
+        // Characters are counted, not bytes.
+        tl.append("ééé".to_string());
+        assert_eq!(tl.first_exceeding(13), None);
+    }
+
+    #[test]
+    fn transaction_log_values_can_be_listed_by_frequency() {
+        let mut tl = TransactionLog::new();
+        assert!(tl.by_frequency().is_empty());
+
+        for v in ["a", "b", "a", "c", "b", "a", "d"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(
+            tl.by_frequency(),
+            [
+                ("a".to_string(), 3),
+                ("b".to_string(), 2),
+                ("c".to_string(), 1),
+                ("d".to_string(), 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn repeated_values_can_be_appended_to_transaction_log() {
+        let mut tl = TransactionLog::new();
+        tl.append_repeated("x".to_string(), 0);
+        assert_eq!(tl.length, 0);
+
+        tl.append_repeated("x".to_string(), 3);
+        assert_eq!(tl.length, 3);
+        assert_eq!(tl.iter().collect::<Vec<_>>(), ["x", "x", "x"]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_checked_for_being_a_subsequence() {
+        let mut reference = TransactionLog::new();
+        for v in ["a", "b", "c"] {
+            reference.append(v.to_string());
+        }
+        let mut ordered = TransactionLog::new();
+        ordered.append("a".to_string());
+        ordered.append("c".to_string());
+        let mut reversed = TransactionLog::new();
+        reversed.append("c".to_string());
+        reversed.append("a".to_string());
+
+        assert!(ordered.is_subsequence_of(&reference));
+        assert!(!reversed.is_subsequence_of(&reference));
+        assert!(TransactionLog::new().is_subsequence_of(&reference));
+        assert!(!reference.is_subsequence_of(&ordered));
+    }
+
+    #[test]
+    fn transaction_log_running_max_tracks_the_largest_value_so_far() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.running_max().length, 0);
+
+        for v in ["b", "a", "c", "a"] {
+            tl.append(v.to_string());
+        }
+        assert_eq!(
+            tl.running_max().iter().collect::<Vec<_>>(),
+            ["b", "b", "c", "c"]
+        );
+    }
+
+    #[test]
+    fn a_range_can_be_cut_from_the_middle_of_transaction_log() {
+        let mut tl = log_of(&["a", "b", "c", "d", "e"]);
+
+        let cut = tl.cut(1, 3);
+        assert_eq!(values_of(&cut), ["b", "c"]);
+        assert_eq!(values_of(&tl), ["a", "d", "e"]);
+    }
+
+    #[test]
+    fn a_range_can_be_cut_from_the_ends_of_transaction_log() {
+        let mut tl = log_of(&["a", "b", "c", "d", "e"]);
+
+        assert_eq!(values_of(&tl.cut(0, 2)), ["a", "b"]);
+        assert_eq!(values_of(&tl), ["c", "d", "e"]);
+        assert_eq!(values_of(&tl.cut(1, 3)), ["d", "e"]);
+        assert_eq!(values_of(&tl), ["c"]);
+        assert_eq!(values_of(&tl.cut(0, 1)), ["c"]);
+        assert_eq!(values_of(&tl), Vec::<String>::new());
+    }
+
+    #[test]
+    fn cut_entries_keep_handing_out_increasing_sequence_ids() {
+        let mut tl = log_of(&["a", "b", "c", "d"]);
+
+        let mut rest = tl.split_off(2);
+        rest.append("e".to_string());
+        assert_eq!(rest.sequence_ids(), [3, 4, 5]);
+        assert_eq!(tl.sequence_ids(), [1, 2]);
+    }
+
+    #[test]
+    fn cutting_an_invalid_range_leaves_transaction_log_unchanged() {
+        let mut tl = log_of(&["a", "b", "c"]);
+
+        assert_eq!(tl.cut(2, 1).length, 0);
+        assert_eq!(tl.cut(1, 1).length, 0);
+        assert_eq!(tl.cut(1, 4).length, 0);
+        assert_eq!(values_of(&tl), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn a_log_can_be_pasted_into_transaction_log() {
+        let mut tl = log_of(&["a", "b", "c"]);
+
+        tl.paste(1, log_of(&["x", "y"]));
+        assert_eq!(values_of(&tl), ["a", "x", "y", "b", "c"]);
+        tl.paste(0, log_of(&["first"]));
+        tl.paste(tl.length, log_of(&["last"]));
+        tl.paste(3, TransactionLog::new());
+        assert_eq!(values_of(&tl), ["first", "a", "x", "y", "b", "c", "last"]);
+
+        let mut empty = TransactionLog::new();
+        empty.paste(0, log_of(&["a", "b"]));
+        assert_eq!(values_of(&empty), ["a", "b"]);
+    }
+
+    #[test]
+    fn pasted_entries_keep_their_sequence_ids() {
+        let mut tl = log_of(&["a", "b"]);
+
+        tl.concat(log_of(&["c", "d", "e"]));
+        assert_eq!(tl.sequence_ids(), [1, 2, 1, 2, 3]);
+        tl.append("f".to_string());
+        assert_eq!(tl.sequence_ids().last(), Some(&4));
+    }
+
+    #[test]
+    fn cut_and_paste_round_trip() {
+        let mut tl = log_of(&["a", "b", "c", "d"]);
+
+        let cut = tl.cut(1, 3);
+        tl.paste(1, cut);
+        assert_eq!(values_of(&tl), ["a", "b", "c", "d"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "paste index (is 4) should be <= length (is 3)")]
+    fn pasting_past_the_end_of_transaction_log_panics() {
+        let mut tl = log_of(&["a", "b", "c"]);
+        tl.paste(4, log_of(&["x"]));
+    }
+
+    #[test]
+    fn successful_savepoint_keeps_its_changes() {
+        let mut tl = log_of(&["a"]);
+
+        let result: Result<usize, ()> = tl.with_savepoint(|tl| {
+            tl.append("b".to_string());
+            tl.append("c".to_string());
+            Ok(tl.length)
+        });
+        assert_eq!(result, Ok(3));
+        assert_eq!(values_of(&tl), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn failed_savepoint_rolls_back_its_changes() {
+        let mut tl = log_of(&["a"]);
+
+        let result: Result<(), &str> = tl.with_savepoint(|tl| {
+            tl.append("b".to_string());
+            tl.append("c".to_string());
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(values_of(&tl), ["a"]);
+    }
+
+    #[test]
+    fn failed_savepoint_only_restores_the_length() {
+        let mut tl = log_of(&["a", "b"]);
+
+        let result: Result<(), &str> = tl.with_savepoint(|tl| {
+            tl.pop();
+            tl.append("c".to_string());
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(values_of(&tl), ["b", "c"]);
+    }
+
+    #[test]
+    fn oscillations_can_be_collapsed_in_transaction_log() {
+        let mut tl = log_of(&["a", "b", "a", "c"]);
+        assert_eq!(tl.collapse_oscillations(), 1);
+        assert_eq!(values_of(&tl), ["a", "c"]);
+
+        let mut tl = log_of(&["x", "a", "b", "a", "b", "a", "y"]);
+        assert_eq!(tl.collapse_oscillations(), 2);
+        assert_eq!(values_of(&tl), ["x", "a", "y"]);
+
+        // Repeats of the same value aren't oscillations.
+        let mut tl = log_of(&["a", "a", "a"]);
+        assert_eq!(tl.collapse_oscillations(), 0);
+        assert_eq!(values_of(&tl), ["a", "a", "a"]);
+    }
+
+    #[test]
+    fn value_following_a_match_can_be_looked_up() {
+        let tl = log_of(&["key", "val", "other", "key"]);
+
+        assert_eq!(tl.value_after("key"), Some("val".to_string()));
+        assert_eq!(tl.value_after("val"), Some("other".to_string()));
+        assert_eq!(tl.value_after("missing"), None);
+        assert_eq!(log_of(&["key"]).value_after("key"), None);
+    }
+
+    #[test]
+    fn transaction_log_can_be_searched_by_prefix() {
+        let tl = log_of(&["apple", "apricot", "banana"]);
+
+        assert_eq!(tl.prefix_search("ap"), ["apple", "apricot"]);
+        assert_eq!(tl.prefix_search(""), ["apple", "apricot", "banana"]);
+        assert!(tl.prefix_search("cherry").is_empty());
+    }
+
+    #[test]
+    fn transaction_log_entropy_measures_value_diversity() {
+        assert_eq!(TransactionLog::<String>::new().shannon_entropy(), 0.0);
+        assert_eq!(log_of(&["a", "a", "a"]).shannon_entropy(), 0.0);
+        assert!((log_of(&["a", "b", "c", "d"]).shannon_entropy() - 2.0).abs() < 1e-9);
+
+        // p = [1/2, 1/4, 1/4] gives 0.5 + 0.5 + 0.5 bits.
+        let tl = log_of(&["a", "a", "b", "c"]);
+        assert!((tl.shannon_entropy() - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn appends_can_be_gated_by_a_closure() {
+        let mut tl = TransactionLog::new();
+
+        assert!(!tl.append_if("Log Item 1".to_string(), || false));
+        assert_eq!(tl.length, 0);
+        assert!(tl.append_if("Log Item 2".to_string(), || true));
+        assert_eq!(values_of(&tl), ["Log Item 2"]);
+    }
+
+    #[test]
+    fn binary_delta_turns_the_base_into_the_target() {
+        let base = log_of(&["a", "b", "c", "d", "e"]);
+        let target = log_of(&["x", "a", "c", "d", "y", "z"]);
+
+        let delta = target.delta_from(&base);
+        let mut patched = log_of(&["a", "b", "c", "d", "e"]);
+        patched.apply_delta(&delta).unwrap();
+        assert_eq!(values_of(&patched), values_of(&target));
+
+        // Empty logs on either side work too.
+        let mut patched = TransactionLog::new();
+        patched
+            .apply_delta(&target.delta_from(&TransactionLog::new()))
+            .unwrap();
+        assert_eq!(values_of(&patched), values_of(&target));
+        let mut patched = log_of(&["a", "b"]);
+        patched
+            .apply_delta(&TransactionLog::new().delta_from(&patched))
+            .unwrap();
+        assert_eq!(patched.length, 0);
+    }
+
+    #[test]
+    fn binary_delta_runs_are_coalesced() {
+        let base = log_of(&["a", "b", "c", "d"]);
+        let target = log_of(&["a", "b"]);
+
+        // One keep record and one delete record.
+        assert_eq!(target.delta_from(&base).len(), 2 * 9);
+    }
+
+    #[test]
+    fn malformed_binary_delta_leaves_transaction_log_unchanged() {
+        let base = log_of(&["a", "b"]);
+        let delta = log_of(&["a", "c"]).delta_from(&base);
+        let mut tl = log_of(&["a", "b"]);
+
+        assert_eq!(
+            tl.apply_delta(&delta[..delta.len() - 1]),
+            Err(DeltaError::Truncated)
+        );
+        assert_eq!(tl.apply_delta(&[7; 9]), Err(DeltaError::UnknownTag(7)));
+        let mut invalid = vec![DELTA_INSERT];
+        invalid.extend_from_slice(&1u64.to_le_bytes());
+        invalid.push(0xff);
+        assert_eq!(tl.apply_delta(&invalid), Err(DeltaError::InvalidUtf8));
+
+        let mut other_base = log_of(&["a"]);
+        assert_eq!(
+            other_base.apply_delta(&delta),
+            Err(DeltaError::BaseMismatch)
+        );
+        assert_eq!(values_of(&other_base), ["a"]);
+        assert_eq!(values_of(&tl), ["a", "b"]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_shuffled_deterministically() {
+        let tl = log_of(&["a", "b", "c", "d", "e", "f", "g", "h"]);
+
+        let once = values_of(&tl.shuffled(42));
+        assert_eq!(once, values_of(&tl.shuffled(42)));
+        assert_ne!(once, values_of(&tl.shuffled(7)));
+
+        let mut sorted = once.clone();
+        sorted.sort();
+        assert_eq!(sorted, values_of(&tl));
+        assert_eq!(TransactionLog::<String>::new().shuffled(0).length, 0);
+    }
+
+    #[test]
+    fn transaction_log_shuffle_never_starts_from_a_zero_state() {
+        let tl = log_of(&["a", "b", "c", "d", "e"]);
+
+        let shuffled = values_of(&tl.shuffled(0x9e37_79b9_7f4a_7c15));
+        assert_ne!(shuffled, ["b", "c", "d", "e", "a"]);
+        assert_eq!(shuffled, values_of(&tl.shuffled(0)));
+    }
+
+    #[test]
+    fn transaction_log_can_be_checked_for_being_a_palindrome() {
+        assert!(TransactionLog::<String>::new().is_palindrome());
+        assert!(log_of(&["a"]).is_palindrome());
+        assert!(log_of(&["a", "b", "a"]).is_palindrome());
+        assert!(log_of(&["a", "b", "b", "a"]).is_palindrome());
+        assert!(!log_of(&["a", "b", "c"]).is_palindrome());
+        assert!(!log_of(&["a", "b"]).is_palindrome());
+    }
+
+    #[test]
+    fn matching_values_can_be_trimmed_from_both_ends() {
+        let mut tl = log_of(&["x", "a", "b", "x"]);
+        assert_eq!(tl.trim_matches("x"), 2);
+        assert_eq!(values_of(&tl), ["a", "b"]);
+
+        let mut tl = log_of(&["x", "x", "a", "x", "b", "x", "x"]);
+        assert_eq!(tl.trim_matches("x"), 4);
+        assert_eq!(values_of(&tl), ["a", "x", "b"]);
+
+        let mut tl = log_of(&["x", "x"]);
+        assert_eq!(tl.trim_matches("x"), 2);
+        assert_eq!(tl.length, 0);
+    }
+
+    #[test]
+    fn hash_chain_changes_downstream_of_an_edit() {
+        let tl = log_of(&["a", "b", "c", "d"]);
+        let chain = tl.hash_chain();
+        assert_eq!(chain.len(), 4);
+        assert_eq!(chain, log_of(&["a", "b", "c", "d"]).hash_chain());
+
+        let edited = log_of(&["a", "B", "c", "d"]).hash_chain();
+        assert_eq!(edited[0], chain[0]);
+        for i in 1..4 {
+            assert_ne!(edited[i], chain[i]);
+        }
+        // The same values in a different order still chain differently.
+        assert_ne!(
+            log_of(&["b", "a"]).hash_chain()[1],
+            log_of(&["a", "b"]).hash_chain()[1]
+        );
+        assert!(TransactionLog::new().hash_chain().is_empty());
+    }
+
+    #[test]
+    fn hash_chain_verification_locates_tampering() {
+        let mut tl = log_of(&["a", "b", "c", "d"]);
+        let expected = tl.hash_chain();
+        assert_eq!(tl.verify_hash_chain(&expected), None);
+
+        tl.cut(2, 3);
+        tl.paste(2, log_of(&["tampered"]));
+        assert_eq!(tl.verify_hash_chain(&expected), Some(2));
+
+        let mut tl = log_of(&["a", "b", "c", "d"]);
+        tl.pop_back();
+        assert_eq!(tl.verify_hash_chain(&expected), Some(3));
+        tl.append("d".to_string());
+        tl.append("e".to_string());
+        assert_eq!(tl.verify_hash_chain(&expected), Some(4));
+    }
+
+    #[test]
+    fn transaction_log_can_be_split_after_delimiters() {
+        let tl = log_of(&["a", "b;", "c;", "d", "e"]);
+
+        let groups = tl.split_inclusive(|v| v.ends_with(';'));
+        let groups: Vec<Vec<String>> = groups.iter().map(values_of).collect();
+        assert_eq!(groups, [vec!["a", "b;"], vec!["c;"], vec!["d", "e"]]);
+
+        let groups = log_of(&["a;", "b;"]).split_inclusive(|v| v.ends_with(';'));
+        assert_eq!(groups.len(), 2);
+        assert!(TransactionLog::<String>::new()
+            .split_inclusive(|_| true)
+            .is_empty());
+    }
+
+    #[test]
+    fn every_nth_entry_can_be_kept() {
+        let mut tl = log_of(&["0", "1", "2", "3", "4", "5"]);
+        tl.keep_every(1);
+        assert_eq!(tl.length, 6);
+
+        tl.keep_every(2);
+        assert_eq!(values_of(&tl), ["0", "2", "4"]);
+        tl.keep_every(5);
+        assert_eq!(values_of(&tl), ["0"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "keep_every step must be non-zero")]
+    fn keeping_every_zeroth_entry_panics() {
+        log_of(&["a"]).keep_every(0);
+    }
+
+    #[test]
+    fn latest_matching_entry_can_be_found_from_the_tail() {
+        let tl = log_of(&["ERROR 1", "INFO 1", "ERROR 2", "INFO 2"]);
+
+        assert_eq!(
+            tl.last_matching(|v| v.starts_with("ERROR")),
+            Some("ERROR 2".to_string())
+        );
+        assert_eq!(tl.last_matching(|v| v.starts_with("WARN")), None);
+    }
+
+    #[test]
+    fn append_only_log_supports_appends_and_reads() {
+        let mut log = AppendOnlyLog::new();
+        assert!(log.is_empty());
+
+        log.append("Log Item 1".to_string());
+        log.append("Log Item 2".to_string());
+        assert_eq!(log.len(), 2);
+        assert_eq!(log.iter().collect::<Vec<_>>(), ["Log Item 1", "Log Item 2"]);
+        assert_eq!(log.back_iter().rev().count(), 2);
+        assert!(log
+            .as_log()
+            .is_subsequence_of(&log_of(&["Log Item 1", "Log Item 2"])));
+
+        let log = AppendOnlyLog::from(log_of(&["a", "b"]));
+        assert_eq!(values_of(log.as_log()), ["a", "b"]);
+    }
+
+    #[test]
+    fn repetitive_transaction_log_is_estimated_more_compressible() {
+        let mut repetitive = TransactionLog::new();
+        repetitive.append_repeated("heartbeat ok".to_string(), 10);
+        let diverse = log_of(&["alpha", "bravo", "charlie", "delta", "echo"]);
+
+        assert_eq!(repetitive.estimated_compression_ratio(), 10.0);
+        assert_eq!(diverse.estimated_compression_ratio(), 1.0);
+        assert_eq!(TransactionLog::new().estimated_compression_ratio(), 1.0);
+    }
+
+    #[test]
+    fn continuation_lines_are_folded_into_records() {
+        let tl = log_of(&[
+            "  orphan",
+            "panic: boom",
+            "  at main.rs:1",
+            "  at lib.rs:2",
+            "ok",
+            "error: bad",
+            "\tdetail",
+        ]);
+
+        let records: Vec<Vec<String>> =
+            tl.records(|v| v.starts_with(char::is_whitespace)).collect();
+        assert_eq!(
+            records,
+            [
+                vec!["  orphan"],
+                vec!["panic: boom", "  at main.rs:1", "  at lib.rs:2"],
+                vec!["ok"],
+                vec!["error: bad", "\tdetail"]
+            ]
+        );
+        assert_eq!(TransactionLog::<String>::new().records(|_| true).count(), 0);
+    }
+
+    #[test]
+    fn first_and_last_index_of_each_value_can_be_mapped() {
+        let spans = log_of(&["a", "b", "a", "c", "a"]).value_spans();
+
+        assert_eq!(spans.len(), 3);
+        assert_eq!(spans["a"], (0, 4));
+        assert_eq!(spans["b"], (1, 1));
+        assert_eq!(spans["c"], (3, 3));
+    }
+
+    #[test]
+    fn event_log_can_be_materialized_into_current_state() {
+        let tl = log_of(&["set x=1", "set y=5", "set x=2"]);
+
+        let state = tl.materialize(|v| {
+            let (key, value) = v.trim_start_matches("set ").split_once('=').unwrap();
+            (key.to_string(), value.to_string())
+        });
+        assert_eq!(state.len(), 2);
+        assert_eq!(state["x"], "2");
+        assert_eq!(state["y"], "5");
+    }
+
+    #[test]
+    fn capped_iteration_yields_at_most_the_cap() {
+        let tl = log_of(&["a", "b", "c"]);
+
+        assert_eq!(tl.iter_capped(2).collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(tl.iter_capped(5).count(), 3);
+        assert_eq!(tl.iter_capped(0).count(), 0);
+    }
+
+    #[test]
+    fn capped_iteration_stops_on_a_cycle() {
+        let tl = log_of(&["a", "b"]);
+        let (head, tail) = (tl.head.clone().unwrap(), tl.tail.clone().unwrap());
+        tail.borrow_mut().next = Some(head);
+
+        assert_eq!(
+            tl.iter_capped(5).collect::<Vec<_>>(),
+            ["a", "b", "a", "b", "a"]
+        );
+        // Break the cycle again so the nodes can be freed.
+        tail.borrow_mut().next = None;
+    }
+
+    #[test]
+    fn values_in_exactly_one_transaction_log_can_be_listed() {
+        let tl = log_of(&["a", "b", "a", "c"]);
+        let other = log_of(&["b", "c", "d", "d"]);
+
+        assert_eq!(tl.symmetric_difference(&other), ["a", "d"]);
+        assert_eq!(other.symmetric_difference(&tl), ["d", "a"]);
+        assert!(tl.symmetric_difference(&tl).is_empty());
+    }
+
+    #[test]
+    fn dropped_transaction_log_frees_its_nodes() {
+        let tl = log_of(&["a"; 1000]);
+        let mut nodes = Vec::new();
+        let mut current = tl.head.clone();
+        while let Some(node) = current {
+            current = node.borrow().next.clone();
+            nodes.push(Rc::downgrade(&node));
+        }
+        assert_eq!(nodes.len(), 1000);
+        // Only the `next` chain (or `head`) holds a node strongly, plus `tail`
+        // for the last one.
+        assert!(nodes[..999].iter().all(|n| n.strong_count() == 1));
+        assert_eq!(nodes[999].strong_count(), 2);
+
+        drop(tl);
+        assert!(nodes.iter().all(|n| n.upgrade().is_none()));
+    }
+
+    #[test]
+    fn structured_log_lines_can_be_filtered_by_level() {
+        let mut tl = TransactionLog::new();
+        tl.append_log(Level::Debug, "starting");
+        tl.append_log(Level::Warn, "disk almost full");
+        tl.append("[BOGUS] not a level".to_string());
+        tl.append_log(Level::Info, "ready");
+        tl.append_log(Level::Error, "disk full");
+
+        assert_eq!(values_of(&tl)[1], "[WARN] disk almost full");
+        assert_eq!(
+            tl.filter_level(Level::Warn),
+            ["[WARN] disk almost full", "[ERROR] disk full"]
+        );
+        assert_eq!(tl.filter_level(Level::Debug).len(), 4);
+    }
+
+    #[test]
+    fn dropping_a_long_transaction_log_does_not_overflow_the_stack() {
+        let mut tl = TransactionLog::new();
+        for _ in 0..1_000_000 {
+            tl.append(String::new());
+        }
+        assert_eq!(tl.length, 1_000_000);
+    }
+
+    #[test]
+    fn longest_palindromic_range_can_be_found() {
+        assert_eq!(
+            TransactionLog::<String>::new().longest_palindrome_range(),
+            None
+        );
+        assert_eq!(log_of(&["a"]).longest_palindrome_range(), Some((0, 1)));
+
+        let tl = log_of(&["a", "x", "b", "x", "a", "c"]);
+        assert_eq!(tl.longest_palindrome_range(), Some((0, 5)));
+        let tl = log_of(&["c", "a", "b", "b", "a", "d"]);
+        assert_eq!(tl.longest_palindrome_range(), Some((1, 5)));
+        // No run longer than one entry, so the earliest single entry wins.
+        let tl = log_of(&["a", "b", "c"]);
+        assert_eq!(tl.longest_palindrome_range(), Some((0, 1)));
+    }
+
+    #[test]
+    fn transaction_log_can_store_non_string_values() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct Event {
+            id: u32,
+            amount: i64,
+        }
+
+        let mut tl = TransactionLog::new();
+        tl.append(Event { id: 1, amount: 10 });
+        tl.append(Event { id: 2, amount: -5 });
+        tl.append(Event { id: 3, amount: 7 });
+
+        let ids: Vec<u32> = tl.back_iter().rev().map(|e| e.id).collect();
+        assert_eq!(ids, [3, 2, 1]);
+        assert_eq!(tl.pop(), Some(Event { id: 1, amount: 10 }));
+        assert_eq!(tl.pop_back(), Some(Event { id: 3, amount: 7 }));
+        assert_eq!(tl.length, 1);
+        assert_eq!(tl.weighted_sum(|e| e.amount as f64), -5.0);
+    }
+
+    #[test]
+    fn transaction_log_ends_can_be_peeked_without_consuming() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.peek(), None);
+        assert_eq!(tl.peek_back(), None);
+
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+        tl.append("Log Item 3".to_string());
+        for _ in 0..2 {
+            assert_eq!(tl.peek(), Some("Log Item 1".to_string()));
+            assert_eq!(tl.peek_back(), Some("Log Item 3".to_string()));
+        }
+        assert_eq!(values_of(&tl), ["Log Item 1", "Log Item 2", "Log Item 3"]);
+    }
+
+    #[test]
+    fn transaction_log_can_spill_old_entries_to_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tl = TransactionLog::new();
+        tl.append("Log Item 1".to_string());
+        tl.append("Log Item 2".to_string());
+        tl.spill_after(2, dir.path().join("spill.log")).unwrap();
+        for i in 3..=6 {
+            tl.append(format!("Log Item {}", i));
+        }
+
+        assert_eq!(values_of(&tl), ["Log Item 5", "Log Item 6"]);
+        assert!(tl.last_spill_error().is_none());
+        let all: Vec<String> = tl.iter_all().unwrap().collect::<io::Result<_>>().unwrap();
+        let expected: Vec<String> = (1..=6).map(|i| format!("Log Item {}", i)).collect();
+        assert_eq!(all, expected);
+    }
+
+    #[test]
+    fn spilling_waits_for_savepoints_and_scripts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tl = log_of(&["a", "b"]);
+        tl.spill_after(2, dir.path().join("spill.log")).unwrap();
+        let all = |tl: &TransactionLog| -> Vec<String> {
+            tl.iter_all().unwrap().collect::<io::Result<_>>().unwrap()
+        };
+
+        let result: Result<(), &str> = tl.with_savepoint(|tl| {
+            tl.append("c".to_string());
+            assert_eq!(tl.length, 3);
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(all(&tl), ["a", "b"]);
+
+        assert_eq!(
+            tl.run_script("append c\npop\npop\npop\npop"),
+            Err(ScriptError::EmptyLog { line: 5 })
+        );
+        assert_eq!(all(&tl), ["a", "b"]);
+
+        let result: Result<(), ()> = tl.with_savepoint(|tl| {
+            tl.append("c".to_string());
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(values_of(&tl), ["b", "c"]);
+        assert_eq!(all(&tl), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn reachable_count_matches_length_of_well_formed_log() {
+        let mut tl = TransactionLog::new();
+        assert_eq!(tl.reachable_count(), 0);
+
+        for i in 1..=4 {
+            tl.append(format!("Log Item {}", i));
+        }
+        tl.pop();
+        tl.pop_back();
+        assert_eq!(tl.reachable_count(), tl.length);
+        assert_eq!(tl.reachable_count(), 2);
+
+        tl.length = 5;
+        assert_eq!(tl.reachable_count(), 2);
+    }
+
+    #[test]
+    fn script_commands_are_applied_in_order() {
+        let mut tl = log_of(&["old"]);
+        let script = "clear\nappend Log Item 2\n\npush_front Log Item 1\nappend Log Item 3\npop\n";
+
+        assert_eq!(tl.run_script(script), Ok(()));
+        assert_eq!(values_of(&tl), ["Log Item 2", "Log Item 3"]);
+    }
+
+    #[test]
+    fn failing_script_is_rolled_back_entirely() {
+        let mut tl = log_of(&["a", "b"]);
+
+        assert_eq!(
+            tl.run_script("append c\nfrobnicate\n"),
+            Err(ScriptError::Malformed {
+                line: 2,
+                text: "frobnicate".to_string()
+            })
+        );
+        assert_eq!(values_of(&tl), ["a", "b"]);
+
+        let script = "pop\npush_front z\nclear\nappend c\npop\npop";
+        assert_eq!(
+            tl.run_script(script),
+            Err(ScriptError::EmptyLog { line: 6 })
+        );
+        assert_eq!(values_of(&tl), ["a", "b"]);
+    }
+
+    #[test]
+    fn failing_script_puts_back_the_original_entries() {
+        let mut tl = log_of(&["a", "b"]);
+        tl.replace_at(1, "b2".to_string());
+
+        let script = "pop\nclear\nappend q\npop\npop\npop";
+        assert_eq!(
+            tl.run_script(script),
+            Err(ScriptError::EmptyLog { line: 5 })
+        );
+        assert_eq!(tl.sequence_ids(), [1, 2]);
+        assert_eq!(
+            tl.iter_versioned().collect::<Vec<_>>(),
+            [("a".to_string(), 0), ("b2".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn length_percentiles_use_nearest_rank() {
+        assert_eq!(TransactionLog::new().length_percentile(0.5), None);
+
+        let tl = log_of(&[
+            "aaaaaaa",
+            "a",
+            "aaaaaaaaaa",
+            "aaa",
+            "aa",
+            "aaaaa",
+            "aaaa",
+            "aaaaaaaaa",
+            "aaaaaa",
+            "aaaaaaaa",
+        ]);
+        assert_eq!(tl.length_percentile(0.5), Some(5));
+        assert_eq!(tl.length_percentile(0.9), Some(9));
+        assert_eq!(tl.length_percentile(0.0), Some(1));
+        assert_eq!(tl.length_percentile(1.0), Some(10));
+        assert_eq!(log_of(&["héllo"]).length_percentile(0.5), Some(5));
+    }
+
+    #[test]
+    fn cloned_transaction_log_is_independent() {
+        let tl = log_of(&["Log Item 1", "Log Item 2", "Log Item 3"]);
+        let mut copy = tl.clone();
+        assert_eq!(copy.sequence_ids(), tl.sequence_ids());
+
+        while copy.pop().is_some() {}
+        copy.append("Log Item 4".to_string());
+
+        assert_eq!(tl.length, 3);
+        assert_eq!(values_of(&tl), ["Log Item 1", "Log Item 2", "Log Item 3"]);
+        assert_eq!(values_of(&copy), ["Log Item 4"]);
+        assert_eq!(copy.sequence_ids(), [4]);
+    }
+
+    #[test]
+    fn short_entries_can_be_merged_up_to_a_length() {
+        let mut tl = log_of(&["a", "bb", "c", "this one is long", "d", "ee", "f"]);
+        tl.merge_short(6, ", ");
+
+        assert_eq!(
+            values_of(&tl),
+            ["a, bb", "c", "this one is long", "d, ee", "f"]
+        );
+        assert_eq!(tl.sequence_ids(), [1, 3, 4, 5, 7]);
+        assert!(tl.iter().all(|v| v.len() <= 6 || v == "this one is long"));
+
+        let mut tl = log_of(&["a", "b", "c", "d"]);
+        tl.merge_short(usize::MAX, "");
+        assert_eq!(values_of(&tl), ["abcd"]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_collected_from_an_iterator() {
+        let tl: TransactionLog = vec!["a", "b", "c"].into_iter().map(String::from).collect();
+        assert_eq!(values_of(&tl), ["a", "b", "c"]);
+        assert_eq!(tl.sequence_ids(), [1, 2, 3]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_extended() {
+        let mut tl = log_of(&["a", "b"]);
+        tl.extend(vec!["c".to_string(), "d".to_string()]);
+        tl.extend(Vec::new());
+        assert_eq!(values_of(&tl), ["a", "b", "c", "d"]);
+        assert_eq!(tl.length, 4);
+    }
+
+    #[test]
+    fn edits_bump_only_the_edited_entrys_generation() {
+        let mut tl = log_of(&["a", "b", "c"]);
+        assert_eq!(
+            tl.iter_versioned().collect::<Vec<_>>(),
+            [
+                ("a".to_string(), 0),
+                ("b".to_string(), 0),
+                ("c".to_string(), 0)
+            ]
+        );
+
+        assert_eq!(tl.replace_at(1, "B".to_string()), "b");
+        tl.replace_at(1, "BB".to_string());
+        tl.merge_short(4, "+");
+        assert_eq!(
+            tl.iter_versioned().collect::<Vec<_>>(),
+            [("a+BB".to_string(), 1), ("c".to_string(), 0)]
+        );
+        assert_eq!(
+            tl.clone().iter_versioned().next(),
+            Some(("a+BB".to_string(), 1))
+        );
+    }
+
+    #[test]
+    fn transaction_log_can_be_built_from_a_generator() {
+        let mut n = 0;
+        let tl = TransactionLog::from_fn(|| {
+            n += 1;
+            (n <= 3).then(|| format!("Log Item {}", n))
+        });
+        assert_eq!(values_of(&tl), ["Log Item 1", "Log Item 2", "Log Item 3"]);
+        assert_eq!(n, 4);
+    }
+
+    #[test]
+    fn transaction_log_can_be_rolled_back_to_a_checkpoint() {
+        let mut tl = log_of(&["a", "b"]);
+        let cp = tl.checkpoint();
+        tl.append("c".to_string());
+        tl.append("d".to_string());
+        assert_eq!(tl.rollback_to(cp), Ok(()));
+        assert_eq!(values_of(&tl), ["a", "b"]);
+
+        let cp = tl.checkpoint();
+        assert_eq!(tl.rollback_to(cp), Ok(()));
+        assert_eq!(values_of(&tl), ["a", "b"]);
+
+        let mut empty = TransactionLog::new();
+        let cp = empty.checkpoint();
+        empty.append("a".to_string());
+        assert_eq!(empty.rollback_to(cp), Ok(()));
+        assert_eq!(empty.length, 0);
+    }
+
+    #[test]
+    fn rolling_back_to_a_removed_checkpoint_fails() {
+        let mut tl = log_of(&["a", "b"]);
+        let cp = tl.checkpoint();
+        tl.append("c".to_string());
+        tl.pop_back();
+        tl.pop_back();
+        tl.append("d".to_string());
+        assert_eq!(tl.rollback_to(cp), Err(CheckpointError));
+        assert_eq!(values_of(&tl), ["a", "d"]);
+
+        // The checkpointed entry still exists, but in another log.
+        let cp = tl.checkpoint();
+        let moved = tl.cut(1, 2);
+        tl.append("e".to_string());
+        assert_eq!(tl.rollback_to(cp), Err(CheckpointError));
+        assert_eq!(values_of(&tl), ["a", "e"]);
+        assert_eq!(values_of(&moved), ["d"]);
+
+        // Entries before the checkpoint were popped from the front.
+        let mut tl = log_of(&["a", "b"]);
+        let cp = tl.checkpoint();
+        tl.pop();
+        tl.append("c".to_string());
+        assert_eq!(tl.rollback_to(cp), Err(CheckpointError));
+        assert_eq!(values_of(&tl), ["b", "c"]);
+
+        // An entry in the middle was removed.
+        let mut tl = log_of(&["a", "b", "c"]);
+        let cp = tl.checkpoint();
+        let _ = tl.cut(1, 2);
+        assert_eq!(tl.rollback_to(cp), Err(CheckpointError));
+        assert_eq!(values_of(&tl), ["a", "c"]);
+    }
+
+    #[test]
+    fn running_checksum_matches_recomputation() {
+        let mut tl = log_of(&["a", "b"]);
+        assert_eq!(tl.checksum(), log_of(&["b", "a"]).checksum());
+
+        tl.append("c".to_string());
+        tl.pop();
+        tl.append_front("z".to_string());
+        tl.append("d".to_string());
+        tl.pop_back();
+        tl.replace_at(1, "y".to_string());
+        assert_eq!(tl.checksum(), log_of(&["z", "y", "c"]).checksum());
+
+        let cut = tl.cut(0, 1);
+        tl.remove_blank();
+        assert_eq!(tl.checksum(), log_of(&["y", "c"]).checksum());
+        assert_eq!(cut.checksum(), log_of(&["z"]).checksum());
+        assert_ne!(tl.checksum(), cut.checksum());
+
+        while tl.pop().is_some() {}
+        assert_eq!(tl.checksum(), TransactionLog::<String>::new().checksum());
+    }
+
+    #[test]
+    fn entries_can_be_looked_up_by_index_from_either_end() {
+        let tl = log_of(&["a", "b", "c", "d", "e"]);
+        assert_eq!(tl.get(0), Some("a".to_string()));
+        assert_eq!(tl.get(4), Some("e".to_string()));
+        assert_eq!(tl.get(5), None);
+        assert_eq!(TransactionLog::<String>::new().get(0), None);
+
+        // Indexes up to length / 2 are walked to from the head, the rest from
+        // the tail.
+        let walked: Vec<String> = (0..tl.length).filter_map(|i| tl.get(i)).collect();
+        assert_eq!(walked, values_of(&tl));
+        let tl = log_of(&["a", "b", "c", "d"]);
+        let walked: Vec<String> = (0..tl.length).filter_map(|i| tl.get(i)).collect();
+        assert_eq!(walked, values_of(&tl));
+    }
+
+    #[test]
+    fn transaction_log_can_be_cleared() {
+        let mut tl = TransactionLog::new();
+        assert!(tl.is_empty());
+        for i in 0..200_000 {
+            tl.append(i.to_string());
+        }
+        assert!(!tl.is_empty());
+
+        tl.clear();
+        assert_eq!(tl.length, 0);
+        assert!(tl.is_empty());
+        assert_eq!(tl.pop(), None);
+
+        tl.append("Log Item 1".to_string());
+        assert_eq!(values_of(&tl), ["Log Item 1"]);
+        assert_eq!(tl.pop(), Some("Log Item 1".to_string()));
+        assert!(tl.is_empty());
+    }
+
+    #[test]
+    fn indexed_view_is_a_detached_snapshot() {
+        let mut tl = log_of(&["a", "b", "c"]);
+        let view = tl.as_indexed();
+        tl.append("d".to_string());
+        tl.pop();
+
+        assert_eq!(view.len(), 3);
+        assert_eq!(view.get(0).map(String::as_str), Some("a"));
+        assert_eq!(view.get(2).map(String::as_str), Some("c"));
+        assert_eq!(view.get(1).map(String::as_str), Some("b"));
+        assert_eq!(view.get(3), None);
+        assert!(TransactionLog::<String>::new().as_indexed().is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn transaction_log_round_trips_through_json() {
+        let tl = log_of(&["Log Item 1", "Log \"Item\" 2", "Log Item 3"]);
+        let json = serde_json::to_string(&tl).unwrap();
+        assert_eq!(json, r#"["Log Item 1","Log \"Item\" 2","Log Item 3"]"#);
+
+        let restored: TransactionLog = serde_json::from_str(&json).unwrap();
+        assert_eq!(values_of(&restored), values_of(&tl));
+        assert_eq!(
+            restored.back_iter().rev().collect::<Vec<_>>(),
+            ["Log Item 3", "Log \"Item\" 2", "Log Item 1"]
+        );
+        let empty: TransactionLog = serde_json::from_str("[]").unwrap();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn transaction_logs_compare_by_value_sequence() {
+        assert_eq!(log_of(&["a", "b", "c"]), log_of(&["a", "b", "c"]));
+        assert_eq!(log_of(&[]), TransactionLog::new());
+        assert_ne!(log_of(&["a", "b", "c"]), log_of(&["a", "x", "c"]));
+        assert_ne!(log_of(&["a", "b"]), log_of(&["a", "b", "c"]));
+        assert_ne!(log_of(&["a", "b", "c"]), log_of(&["c", "b", "a"]));
+    }
+
+    #[test]
+    fn transaction_log_is_displayed_as_a_list() {
+        assert_eq!(TransactionLog::<String>::new().to_string(), "[]");
+        assert_eq!(log_of(&["Log Item 1"]).to_string(), "[Log Item 1]");
+        assert_eq!(
+            log_of(&["Log Item 1", "Log Item 2", "Log Item 3"]).to_string(),
+            "[Log Item 1, Log Item 2, Log Item 3]"
+        );
+        let mut tl = log_of(&["a", "b", "c"]);
+        tl.pop();
+        assert_eq!(format!("{}", tl), "[b, c]");
+    }
+
+    #[test]
+    fn cursor_can_edit_the_middle_of_transaction_log() {
+        let mut tl = log_of(&["a", "b", "oops", "d"]);
+        let mut cursor = tl.cursor_mut();
+        cursor.move_next();
+        cursor.move_next();
+        assert_eq!(cursor.current(), Some("oops".to_string()));
+        assert_eq!(cursor.remove_current(), Some("oops".to_string()));
+        assert_eq!(cursor.current(), Some("d".to_string()));
+        cursor.insert_before("c".to_string());
+        cursor.insert_after("e".to_string());
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some("c".to_string()));
+
+        assert_eq!(values_of(&tl), ["a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn cursor_can_remove_and_insert_at_the_ends() {
+        let mut tl = log_of(&["a", "b", "c"]);
+        let mut cursor = tl.cursor_mut();
+        assert_eq!(cursor.remove_current(), Some("a".to_string()));
+        cursor.move_next();
+        assert_eq!(cursor.remove_current(), Some("c".to_string()));
+        // Removing the tail leaves the cursor on the ghost.
+        assert_eq!(cursor.current(), None);
+        assert_eq!(cursor.remove_current(), None);
+        cursor.insert_after("front".to_string());
+        cursor.insert_before("back".to_string());
+        cursor.move_prev();
+        assert_eq!(cursor.current(), Some("back".to_string()));
+        assert_eq!(values_of(&tl), ["front", "b", "back"]);
+
+        let mut cursor = tl.cursor_mut();
+        while cursor.remove_current().is_some() {}
+        assert!(tl.is_empty());
+        assert_eq!(values_of(&tl), Vec::<String>::new());
+
+        let mut cursor = tl.cursor_mut();
+        cursor.insert_before("only".to_string());
+        assert_eq!(values_of(&tl), ["only"]);
+    }
+
+    #[test]
+    fn entries_can_be_retained_in_place() {
+        let mut tl = log_of(&["drop", "a", "drop", "b", "drop"]);
+        tl.retain(|v| v != "drop");
+        assert_eq!(values_of(&tl), ["a", "b"]);
+
+        let mut tl = log_of(&["a", "b", "c"]);
+        tl.retain(|v| v != "a");
+        assert_eq!(values_of(&tl), ["b", "c"]);
+        tl.retain(|v| v != "c");
+        assert_eq!(values_of(&tl), ["b"]);
+        tl.append("d".to_string());
+        assert_eq!(values_of(&tl), ["b", "d"]);
+
+        let mut visited = Vec::new();
+        tl.retain(|v| {
+            visited.push(v.clone());
+            false
+        });
+        assert_eq!(visited, ["b", "d"]);
+        assert!(tl.is_empty());
+        assert_eq!(values_of(&tl), Vec::<String>::new());
+        tl.append("e".to_string());
+        assert_eq!(values_of(&tl), ["e"]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_reversed_in_place() {
+        let mut tl = log_of(&["Log Item 1", "Log Item 2", "Log Item 3"]);
+        let backward: Vec<String> = tl.back_iter().rev().collect();
+        tl.reverse();
+        assert_eq!(values_of(&tl), backward);
+        assert_eq!(tl.pop(), Some("Log Item 3".to_string()));
+        tl.append("Log Item 0".to_string());
+
+        tl.reverse();
+        assert_eq!(values_of(&tl), ["Log Item 0", "Log Item 1", "Log Item 2"]);
+
+        let mut empty = TransactionLog::<String>::new();
+        empty.reverse();
+        assert!(empty.is_empty());
+        let mut one = log_of(&["a"]);
+        one.reverse();
+        assert_eq!(values_of(&one), ["a"]);
+    }
+
+    #[test]
+    fn transaction_logs_can_be_concatenated() {
+        let mut tl = log_of(&["a", "b"]);
+        tl.concat(log_of(&["c", "d", "e"]));
+        assert_eq!(values_of(&tl), ["a", "b", "c", "d", "e"]);
+        assert_eq!(tl.length, 5);
+
+        tl.concat(TransactionLog::new());
+        assert_eq!(values_of(&tl), ["a", "b", "c", "d", "e"]);
+
+        let mut empty = TransactionLog::new();
+        empty.concat(log_of(&["x", "y"]));
+        assert_eq!(values_of(&empty), ["x", "y"]);
+        empty.append("z".to_string());
+        assert_eq!(values_of(&empty), ["x", "y", "z"]);
+    }
+
+    #[test]
+    fn transaction_log_can_be_split_in_two() {
+        let mut tl = log_of(&["a", "b", "c", "d"]);
+        let rest = tl.split_off(1);
+        assert_eq!(values_of(&tl), ["a"]);
+        assert_eq!(values_of(&rest), ["b", "c", "d"]);
+
+        let mut tl = log_of(&["a", "b"]);
+        let rest = tl.split_off(2);
+        assert_eq!(values_of(&tl), ["a", "b"]);
+        assert!(rest.is_empty());
+
+        let rest = tl.split_off(0);
+        assert!(tl.is_empty());
+        assert_eq!(values_of(&tl), Vec::<String>::new());
+        assert_eq!(values_of(&rest), ["a", "b"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "split_off index (is 3) should be <= length (is 2)")]
+    fn splitting_past_the_end_of_transaction_log_panics() {
+        log_of(&["a", "b"]).split_off(3);
+    }
+
+    #[test]
+    fn entries_can_be_looked_up_by_value() {
+        let tl = log_of(&["a", "b", "c", "b"]);
+        assert_eq!(tl.position("a"), Some(0));
+        assert_eq!(tl.position("c"), Some(2));
+        assert_eq!(tl.position("b"), Some(1));
+        assert_eq!(tl.position("z"), None);
+        assert!(tl.contains("b"));
+        assert!(tl.contains(&"c".to_string()));
+        assert!(!tl.contains("z"));
+
+        let empty = TransactionLog::<String>::new();
+        assert_eq!(empty.position("a"), None);
+        assert!(!empty.contains("a"));
+    }
+
+    #[test]
+    fn transaction_log_can_be_converted_to_a_vec() {
+        let tl = log_of(&["a", "b", "c"]);
+        assert_eq!(tl.to_vec(), ["a", "b", "c"]);
+        assert_eq!(tl.into_vec(), ["a", "b", "c"]);
+        assert!(TransactionLog::<String>::new().to_vec().is_empty());
+
+        let marker = Rc::new(());
+        let mut tl = TransactionLog::new();
+        tl.append(marker.clone());
+        tl.append(marker.clone());
+        let values = tl.into_vec();
+        assert_eq!(values.len(), 2);
+        drop(values);
+        assert_eq!(Rc::strong_count(&marker), 1);
+    }
+
+    #[test]
+    fn indexed_iteration_counts_from_the_head() {
+        let tl = log_of(&["a", "b", "c"]);
+        assert_eq!(
+            tl.indexed_iter().collect::<Vec<_>>(),
+            [
+                (0, "a".to_string()),
+                (1, "b".to_string()),
+                (2, "c".to_string())
+            ]
+        );
+        assert_eq!(
+            tl.indexed_back_iter().rev().collect::<Vec<_>>(),
+            [
+                (2, "c".to_string()),
+                (1, "b".to_string()),
+                (0, "a".to_string())
+            ]
+        );
+        assert_eq!(
+            TransactionLog::<String>::new().indexed_back_iter().next(),
+            None
+        );
+    }
+
+    #[test]
+    fn indexed_iteration_directions_can_be_mixed() {
+        let tl = log_of(&["a", "b", "c", "d"]);
+        let mut values = tl.indexed_iter();
+        assert_eq!(values.next(), Some((0, "a".to_string())));
+        assert_eq!(values.next(), Some((1, "b".to_string())));
+        assert_eq!(values.next_back(), Some((2, "c".to_string())));
+        assert_eq!(values.next_back(), Some((1, "b".to_string())));
+        assert_eq!(values.next(), Some((0, "a".to_string())));
+        assert_eq!(values.next(), Some((1, "b".to_string())));
+    }
+
+    #[test]
+    fn capped_transaction_log_evicts_the_oldest_entries() {
+        let mut tl = TransactionLog::with_capacity(3);
+        for i in 1..=5 {
+            tl.append(format!("Log Item {}", i));
+            assert!(tl.length <= 3);
+        }
+        assert_eq!(values_of(&tl), ["Log Item 3", "Log Item 4", "Log Item 5"]);
+
+        tl.set_capacity(Some(1));
+        assert_eq!(values_of(&tl), ["Log Item 5"]);
+        tl.set_capacity(None);
+        tl.append("Log Item 6".to_string());
+        assert_eq!(values_of(&tl), ["Log Item 5", "Log Item 6"]);
+
+        let mut none_kept = TransactionLog::with_capacity(0);
+        none_kept.append("Log Item 1".to_string());
+        assert!(none_kept.is_empty());
+    }
+
+    #[test]
+    fn eviction_waits_for_savepoints_and_scripts() {
+        let capped = || {
+            let mut tl = TransactionLog::with_capacity(3);
+            tl.extend(["a", "b", "c"].map(String::from));
+            tl
+        };
+
+        let mut tl = capped();
+        let result: Result<(), &str> = tl.with_savepoint(|tl| {
+            tl.append("d".to_string());
+            assert_eq!(tl.length, 4);
+            Err("failed")
+        });
+        assert_eq!(result, Err("failed"));
+        assert_eq!(values_of(&tl), ["a", "b", "c"]);
+
+        let result: Result<(), ()> = tl.with_savepoint(|tl| {
+            tl.append("d".to_string());
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(values_of(&tl), ["b", "c", "d"]);
+
+        let mut tl = capped();
+        assert_eq!(
+            tl.run_script("append d\npop\npop\npop\npop\npop"),
+            Err(ScriptError::EmptyLog { line: 6 })
+        );
+        assert_eq!(values_of(&tl), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn every_way_of_adding_entries_respects_the_cap() {
+        let mut tl = TransactionLog::with_capacity(2);
+        for value in ["a", "b", "c"] {
+            tl.append_front(value.to_string());
+        }
+        assert_eq!(values_of(&tl), ["b", "a"]);
+
+        tl.paste(1, log_of(&["x", "y"]));
+        assert_eq!(values_of(&tl), ["y", "a"]);
+        tl.concat(log_of(&["z"]));
+        assert_eq!(values_of(&tl), ["a", "z"]);
+
+        let mut cursor = tl.cursor_mut();
+        cursor.insert_after("w".to_string());
+        assert_eq!(values_of(&tl), ["w", "z"]);
+    }
+
+    #[test]
+    fn eviction_invalidates_checkpoints() {
+        let mut tl = TransactionLog::with_capacity(3);
+        tl.extend(["a", "b", "c"].map(String::from));
+        let cp = tl.checkpoint();
+        tl.append("d".to_string());
+        assert_eq!(tl.rollback_to(cp), Err(CheckpointError));
+        assert_eq!(values_of(&tl), ["b", "c", "d"]);
+    }
+
+    #[test]
+    fn entries_can_be_undone_and_redone() {
+        let mut tl = log_of(&["Log Item 1", "Log Item 2", "Log Item 3"]);
+        assert_eq!(tl.undo(), Some("Log Item 3".to_string()));
+        assert_eq!(tl.undo(), Some("Log Item 2".to_string()));
+        assert_eq!(values_of(&tl), ["Log Item 1"]);
+
+        assert_eq!(tl.redo(), Some("Log Item 2".to_string()));
+        assert_eq!(tl.redo(), Some("Log Item 3".to_string()));
+        assert_eq!(tl.redo(), None);
+        assert_eq!(values_of(&tl), ["Log Item 1", "Log Item 2", "Log Item 3"]);
+    }
+
+    #[test]
+    fn undoing_past_the_start_returns_none() {
+        let mut tl = log_of(&["Log Item 1"]);
+        assert_eq!(tl.undo(), Some("Log Item 1".to_string()));
+        assert_eq!(tl.undo(), None);
+        assert!(tl.is_empty());
+        assert_eq!(tl.redo(), Some("Log Item 1".to_string()));
+        assert_eq!(values_of(&tl), ["Log Item 1"]);
+    }
+
+    #[test]
+    fn clearing_drops_the_redo_history() {
+        let mut tl = log_of(&["Log Item 1", "Log Item 2"]);
+        tl.undo();
+        tl.clear();
+        assert_eq!(tl.redo(), None);
+        assert!(tl.is_empty());
+    }
+
+    #[test]
+    fn script_clear_drops_the_redo_history_unless_rolled_back() {
+        let mut tl = log_of(&["Log Item 1", "Log Item 2"]);
+        tl.undo();
+        assert_eq!(
+            tl.run_script("clear\nappend Log Item 3\npop\npop"),
+            Err(ScriptError::EmptyLog { line: 4 })
+        );
+        assert_eq!(tl.redo(), Some("Log Item 2".to_string()));
+
+        tl.undo();
+        assert_eq!(tl.run_script("clear"), Ok(()));
+        assert_eq!(tl.redo(), None);
+        assert!(tl.is_empty());
+    }
+
+    #[test]
+    fn popping_keeps_the_redo_history() {
+        let mut tl = log_of(&["Log Item 1", "Log Item 2", "Log Item 3"]);
+        tl.undo();
+        tl.pop();
+        assert_eq!(tl.redo(), Some("Log Item 3".to_string()));
+        assert_eq!(values_of(&tl), ["Log Item 2", "Log Item 3"]);
+    }
+
+    #[test]
+    fn appending_clears_the_redo_history() {
+        let mut tl = log_of(&["Log Item 1", "Log Item 2"]);
+        tl.undo();
+        tl.append("Log Item 3".to_string());
+        assert_eq!(tl.redo(), None);
+        assert_eq!(values_of(&tl), ["Log Item 1", "Log Item 3"]);
     }
 }
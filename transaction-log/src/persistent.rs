@@ -0,0 +1,183 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use crate::{ListIterator, TransactionLog};
+
+/// A [`TransactionLog`] backed by a write-ahead file. Every append is
+/// written to the file, one line per entry, before it's added in memory, so
+/// reopening the file brings back every entry that made it to disk.
+///
+/// Newlines, carriage returns and backslashes in values are escaped as `\n`,
+/// `\r` and `\\`.
+#[derive(Debug)]
+pub struct PersistentTransactionLog {
+    log: TransactionLog,
+    file: File,
+}
+
+impl PersistentTransactionLog {
+    /// Open the log stored at `path`, creating the file if it doesn't exist,
+    /// and replay the entries already in it. A partial trailing line left
+    /// behind by an interrupted write is cut off so later appends start on a
+    /// fresh line.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<PersistentTransactionLog> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        let complete = complete_lines(&contents);
+        if complete.len() < contents.len() {
+            file.set_len(complete.len() as u64)?;
+        }
+        Ok(PersistentTransactionLog {
+            log: replay(complete),
+            file,
+        })
+    }
+
+    /// Read the log stored at `path` back into memory without opening it for
+    /// appends. Corrupt lines and a partial trailing line are skipped.
+    pub fn replay_from<P: AsRef<Path>>(path: P) -> io::Result<TransactionLog> {
+        let mut contents = Vec::new();
+        File::open(path)?.read_to_end(&mut contents)?;
+        Ok(replay(complete_lines(&contents)))
+    }
+
+    /// Write `value` to the file, then append it in memory. If the write
+    /// fails the value isn't appended.
+    pub fn append(&mut self, value: String) -> io::Result<()> {
+        let mut line = escape(&value);
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.log.append(value);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.log.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.log.length == 0
+    }
+
+    pub fn iter(&self) -> ListIterator<String> {
+        self.log.iter()
+    }
+
+    /// Borrow the in-memory log for any other read-only operation.
+    pub fn as_log(&self) -> &TransactionLog {
+        &self.log
+    }
+}
+
+/// Return `contents` up to and including its last newline.
+fn complete_lines(contents: &[u8]) -> &[u8] {
+    let end = contents
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |i| i + 1);
+    &contents[..end]
+}
+
+/// Rebuild a `TransactionLog` from complete, newline terminated lines,
+/// skipping any line that isn't valid.
+fn replay(lines: &[u8]) -> TransactionLog {
+    let Some(lines) = lines.strip_suffix(b"\n") else {
+        return TransactionLog::new();
+    };
+    lines
+        .split(|&byte| byte == b'\n')
+        .filter_map(|line| unescape(std::str::from_utf8(line).ok()?))
+        .collect()
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Undo [`escape`], returning `None` for an unknown or dangling escape.
+fn unescape(line: &str) -> Option<String> {
+    let mut value = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        value.push(match c {
+            '\\' => match chars.next()? {
+                '\\' => '\\',
+                'n' => '\n',
+                'r' => '\r',
+                _ => return None,
+            },
+            c => c,
+        });
+    }
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_survive_reopening_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+
+        let mut log = PersistentTransactionLog::open(&path).unwrap();
+        assert!(log.is_empty());
+        for value in ["Log Item 1", "two\nlines", "back\\slash\\n", ""] {
+            log.append(value.to_string()).unwrap();
+        }
+        drop(log);
+
+        let mut log = PersistentTransactionLog::open(&path).unwrap();
+        assert_eq!(
+            log.iter().collect::<Vec<_>>(),
+            ["Log Item 1", "two\nlines", "back\\slash\\n", ""]
+        );
+        log.append("Log Item 5".to_string()).unwrap();
+        let replayed = PersistentTransactionLog::replay_from(&path).unwrap();
+        assert_eq!(replayed.length, 5);
+        assert_eq!(replayed.back_iter().next(), Some("Log Item 5".to_string()));
+    }
+
+    #[test]
+    fn corrupt_and_partial_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.txt");
+        std::fs::write(
+            &path,
+            b"Log Item 1\nbad \\q escape\n\xff\nLog Item 2\nLog It",
+        )
+        .unwrap();
+
+        let replayed = PersistentTransactionLog::replay_from(&path).unwrap();
+        assert_eq!(
+            replayed.iter().collect::<Vec<_>>(),
+            ["Log Item 1", "Log Item 2"]
+        );
+
+        // Opening cuts the partial line off before appending after it.
+        let mut log = PersistentTransactionLog::open(&path).unwrap();
+        log.append("Log Item 3".to_string()).unwrap();
+        let replayed = PersistentTransactionLog::replay_from(&path).unwrap();
+        assert_eq!(
+            replayed.iter().collect::<Vec<_>>(),
+            ["Log Item 1", "Log Item 2", "Log Item 3"]
+        );
+        assert_eq!(log.len(), 3);
+    }
+}
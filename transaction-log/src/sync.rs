@@ -0,0 +1,201 @@
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+
+type SyncLink<T> = Option<Arc<Mutex<SyncNode<T>>>>;
+
+#[derive(Debug)]
+struct SyncNode<T> {
+    value: T,
+    prev: Option<Weak<Mutex<SyncNode<T>>>>,
+    next: SyncLink<T>,
+}
+
+#[derive(Debug)]
+struct Ends<T> {
+    head: SyncLink<T>,
+    tail: SyncLink<T>,
+    length: usize,
+}
+
+/// A [`TransactionLog`](crate::TransactionLog) that can be shared between
+/// threads, with `append` and `pop` taking `&self`.
+///
+/// Locking order: the lock guarding the log's ends is always taken before any
+/// node's lock, and a node's lock is never held while another lock is taken.
+/// Iterators only ever lock one node at a time and never the ends, so they
+/// can't deadlock with `append` or `pop`.
+#[derive(Debug)]
+pub struct SyncTransactionLog<T = String> {
+    ends: Mutex<Ends<T>>,
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().expect("Something is terribly wrong")
+}
+
+impl<T> SyncTransactionLog<T> {
+    pub fn new() -> SyncTransactionLog<T> {
+        SyncTransactionLog {
+            ends: Mutex::new(Ends {
+                head: None,
+                tail: None,
+                length: 0,
+            }),
+        }
+    }
+
+    /// Append a new value at the end of the `SyncTransactionLog`.
+    pub fn append(&self, value: T) {
+        let new_node = Arc::new(Mutex::new(SyncNode {
+            value,
+            prev: None,
+            next: None,
+        }));
+        let mut ends = lock(&self.ends);
+        match ends.tail.take() {
+            Some(old_node) => {
+                lock(&old_node).next = Some(new_node.clone());
+                lock(&new_node).prev = Some(Arc::downgrade(&old_node));
+            }
+            None => ends.head = Some(new_node.clone()),
+        }
+        ends.tail = Some(new_node);
+        ends.length += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        lock(&self.ends).length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over the values from head to tail. Entries appended while
+    /// iterating may or may not be seen, and an entry popped while the
+    /// iterator is on it ends the iteration.
+    pub fn iter(&self) -> SyncIter<T> {
+        SyncIter {
+            current_link: lock(&self.ends).head.clone(),
+        }
+    }
+}
+
+impl<T: Clone> SyncTransactionLog<T> {
+    /// Pop a value from the front of the `SyncTransactionLog`.
+    pub fn pop(&self) -> Option<T> {
+        let head_node = {
+            let mut ends = lock(&self.ends);
+            let head_node = ends.head.take()?;
+            let next_node = lock(&head_node).next.take();
+            match &next_node {
+                Some(next_node) => lock(next_node).prev = None,
+                None => ends.tail = None,
+            }
+            ends.head = next_node;
+            ends.length -= 1;
+            head_node
+        };
+        // An iterator may still be holding on to the node, in which case the
+        // value has to be copied out instead.
+        match Arc::try_unwrap(head_node) {
+            Ok(node) => Some(
+                node.into_inner()
+                    .expect("Something is terribly wrong")
+                    .value,
+            ),
+            Err(node) => Some(lock(&node).value.clone()),
+        }
+    }
+}
+
+impl<T> Default for SyncTransactionLog<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for SyncTransactionLog<T> {
+    fn drop(&mut self) {
+        // Break the chain one link at a time, see `TransactionLog::clear`.
+        let ends = self.ends.get_mut().expect("Something is terribly wrong");
+        ends.tail.take();
+        let mut current = ends.head.take();
+        while let Some(node) = current {
+            current = lock(&node).next.take();
+        }
+    }
+}
+
+/// Iterator returned by [`SyncTransactionLog::iter`].
+pub struct SyncIter<T> {
+    current_link: SyncLink<T>,
+}
+
+impl<T: Clone> Iterator for SyncIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.current_link.take()?;
+        let current_node = lock(&current);
+        self.current_link = current_node.next.clone();
+        Some(current_node.value.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn sync_transaction_log_is_send_and_sync() {
+        assert_send_sync::<SyncTransactionLog>();
+    }
+
+    #[test]
+    fn sync_transaction_log_can_be_appended_from_several_threads() {
+        let log = Arc::new(SyncTransactionLog::new());
+        let workers: Vec<_> = (0..8)
+            .map(|worker| {
+                let log = Arc::clone(&log);
+                thread::spawn(move || {
+                    for i in 0..100 {
+                        log.append(format!("{}:{}", worker, i));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        assert_eq!(log.len(), 800);
+        assert_eq!(log.iter().count(), 800);
+        // Every worker's entries keep the order it appended them in.
+        let values: Vec<String> = log.iter().collect();
+        for worker in 0..8 {
+            let prefix = format!("{}:", worker);
+            let order: Vec<usize> = values
+                .iter()
+                .filter_map(|v| v.strip_prefix(&prefix)?.parse().ok())
+                .collect();
+            assert_eq!(order, (0..100).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn sync_transaction_log_pops_in_order() {
+        let log = SyncTransactionLog::new();
+        log.append("Log Item 1".to_string());
+        log.append("Log Item 2".to_string());
+
+        let mut iter = log.iter();
+        assert_eq!(log.pop(), Some("Log Item 1".to_string()));
+        assert_eq!(iter.next(), Some("Log Item 1".to_string()));
+        assert_eq!(log.pop(), Some("Log Item 2".to_string()));
+        assert_eq!(log.pop(), None);
+        assert!(log.is_empty());
+    }
+}